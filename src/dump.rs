@@ -0,0 +1,164 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::path::PathBuf;
+use argh::FromArgs;
+use anyhow::Context;
+use autocxx::moveit::moveit;
+use bstr::ByteSlice;
+use crate::sys::lldb;
+use crate::util::{ dump_memory, memory_regions, module_name };
+
+
+/// MyDbg Dump command
+#[derive(FromArgs)]
+pub struct Command {
+    /// dump regions whose name contains this
+    #[argh(option)]
+    region: Option<String>,
+
+    /// dump all readable regions
+    #[argh(switch)]
+    all: bool,
+
+    /// dump regions with these permissions, e.g. rw
+    #[argh(option)]
+    perms: Option<String>,
+
+    /// output directory
+    #[argh(option)]
+    dir: PathBuf,
+}
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.all || self.region.is_some() || self.perms.is_some(),
+            "need --region, --perms or --all"
+        );
+
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let mut error = lldb::SBError::new();
+        }
+
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create dir failed: {:?}", self.dir))?;
+
+        let mut buf: Vec<u8> = Vec::new();
+        let mut manifest = Vec::new();
+
+        for region in memory_regions(process.as_mut()) {
+            if !region.readable {
+                continue
+            }
+
+            let perms = region.perms();
+            let name = region.name.as_deref().unwrap_or_default();
+
+            if let Some(want) = self.region.as_ref() {
+                if name.find(want).is_none() {
+                    continue
+                }
+            }
+
+            if let Some(want) = self.perms.as_ref() {
+                if !want.chars().filter(|&c| c != '-').all(|c| perms.contains(c)) {
+                    continue
+                }
+            }
+
+            let size = region.size()?;
+
+            if size > 4 * 1024 * 1024 * 1024 {
+                writeln!(stdout, "memory region too large: {:?}", region.range)?;
+                continue
+            }
+
+            let filename = format!("{:016x}-{:016x}.bin", region.range.start, region.range.end);
+            let path = self.dir.join(&filename);
+            let mut output = std::fs::File::create(&path)?;
+
+            if let Err(err) = dump_memory(
+                process.as_mut(),
+                &mut buf,
+                region.range.start,
+                size,
+                error.as_mut(),
+                &mut output
+            ) {
+                drop(output);
+                let _ = std::fs::remove_file(&path);
+                writeln!(stdout, "skip {:?}: {:?}", region.range, err)?;
+                continue
+            }
+
+            output.flush()?;
+
+            let module = module_name(target.as_mut(), region.range.start);
+
+            writeln!(
+                stdout,
+                "[{:018p}-{:018p}] {} {:?} -> {}",
+                region.range.start as *const u8,
+                region.range.end as *const u8,
+                perms,
+                name.as_bstr(),
+                filename
+            )?;
+
+            manifest.push(format!(
+                "  {{ \"base\": \"{:#x}\", \"end\": \"{:#x}\", \"perms\": \"{}\", \"name\": {}, \"module\": {}, \"file\": {} }}",
+                region.range.start,
+                region.range.end,
+                perms,
+                json_string(region.name.as_deref()),
+                json_string(module.as_deref()),
+                json_string(Some(filename.as_bytes()))
+            ));
+        }
+
+        let mut output = std::fs::File::create(self.dir.join("manifest.json"))?;
+        writeln!(output, "[")?;
+        writeln!(output, "{}", manifest.join(",\n"))?;
+        writeln!(output, "]")?;
+        output.flush()?;
+
+        writeln!(stdout, "{} regions dumped to {:?}", manifest.len(), self.dir)?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+fn json_string(value: Option<&[u8]>) -> String {
+    use std::fmt::Write;
+
+    let value = match value {
+        Some(value) => value,
+        None => return "null".into()
+    };
+
+    let mut output = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            },
+            c => output.push(c)
+        }
+    }
+    output.push('"');
+    output
+}
+
+#[test]
+fn test_json_string() {
+    assert_eq!(json_string(None), "null");
+    assert_eq!(json_string(Some(b"[heap]")), "\"[heap]\"");
+    assert_eq!(json_string(Some(b"a\"b\\c\n")), "\"a\\\"b\\\\c\\u000a\"");
+}
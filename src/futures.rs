@@ -0,0 +1,257 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::collections::HashSet;
+use argh::FromArgs;
+use autocxx::moveit::moveit;
+use bstr::ByteSlice;
+use crate::sys::lldb;
+use crate::thread::frame_locals;
+
+
+/// MyDbg Futures command
+#[derive(FromArgs)]
+pub struct Command {
+    /// show the N largest future types, default 20
+    #[argh(option, default = "20")]
+    top: usize,
+
+    /// only show future types whose name contains this
+    #[argh(option)]
+    filter: Option<String>,
+}
+
+/// rustc names the state machine types of `async fn` and `async {}` like this.
+const ASYNC_PATTERNS: &[&str] = &[
+    "{async_fn_env#",
+    "{async_block_env#",
+];
+
+struct FutureType {
+    size: u64,
+    name: Vec<u8>,
+    module: Vec<u8>,
+}
+
+struct Member {
+    index: u32,
+    offset: u64,
+    size: u64,
+    name: Vec<u8>,
+    ty: Vec<u8>,
+    is_wrapper: bool,
+    is_variant: bool,
+}
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+        }
+
+        let mut list = Vec::new();
+        let mut seen = HashSet::new();
+
+        let modules = target.as_mut().GetNumModules();
+        for module_idx in 0..modules {
+            moveit!{
+                let mut module = target.as_mut().GetModuleAtIndex(module_idx);
+                let filespec = module.as_ref().GetFileSpec();
+                // eTypeClassAny
+                let types = module.as_mut().GetTypes(u32::MAX);
+            }
+
+            let module_name = cstr!(unsafe filespec.GetFilename())
+                .map(|name| Vec::from(name.to_bytes()))
+                .unwrap_or_default();
+
+            for type_idx in 0..types.GetSize() {
+                moveit!(let mut ty = types.GetTypeAtIndex(type_idx));
+
+                let name = match cstr!(unsafe ty.as_mut().GetName()) {
+                    Some(name) => name.to_bytes(),
+                    None => continue
+                };
+
+                if !is_async_env(name)
+                    || self.filter.as_ref().is_some_and(|filter| name.find(filter).is_none())
+                    || !seen.insert(name.to_vec())
+                {
+                    continue
+                }
+
+                list.push(FutureType {
+                    size: ty.as_mut().GetByteSize(),
+                    name: name.to_vec(),
+                    module: module_name.clone()
+                });
+            }
+        }
+
+        list.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+        writeln!(stdout, "{} future types found", list.len())?;
+        writeln!(stdout)?;
+
+        list.truncate(self.top);
+
+        for item in list.iter() {
+            writeln!(stdout, "{} {:?} in {}", item.size, item.name.as_bstr(), item.module.as_bstr())?;
+
+            let cname = std::ffi::CString::new(item.name.clone())?;
+            moveit!(let mut ty = unsafe { target.as_mut().FindFirstType(cname.as_ptr()) });
+
+            if ty.as_mut().IsValid() {
+                print_members(&mut stdout, ty.as_mut(), 1, false)?;
+            }
+
+            writeln!(stdout)?;
+        }
+
+        // live instances of the listed futures on the current thread
+        moveit!(let mut thread = process.as_mut().GetSelectedThread());
+
+        writeln!(stdout, "thread #{} tid= {}", thread.GetIndexID(), thread.GetThreadID())?;
+
+        let mut count = 0;
+        let frames = thread.as_mut().GetNumFrames();
+        for frame_idx in 0..frames {
+            moveit!(let mut frame = thread.as_mut().GetFrameAtIndex(frame_idx));
+
+            let function = cstr!(unsafe frame.as_mut().GetFunctionName())
+                .map(|name| Vec::from(name.to_bytes()))
+                .unwrap_or_default();
+
+            // by value only, `&mut` or `Pin<&mut>` of a future does not hold it on stack
+            for local in frame_locals(frame.as_mut(), false) {
+                if !list.iter().any(|item| local.ty == item.name) {
+                    continue
+                }
+
+                write!(
+                    stdout,
+                    "#{} {:?}: let {}: {:?} = {};",
+                    frame_idx,
+                    function.as_bstr(),
+                    local.name.as_bstr(),
+                    local.ty.as_bstr(),
+                    local.size
+                )?;
+
+                match local.location.as_ref() {
+                    Some(location) if local.available => writeln!(stdout, " // at {}", location.as_bstr())?,
+                    _ => writeln!(stdout, " // optimized out")?
+                }
+
+                count += 1;
+            }
+        }
+
+        writeln!(stdout, "{} live instances", count)?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Print fields of a future, descending into variants (`Unresumed`, `Suspend0`, ...),
+/// largest field first.
+///
+/// `in_variant` is set for the type of a `$variant$N` member, whose `value` field is
+/// the variant itself rather than a saved local.
+fn print_members(
+    stdout: &mut dyn Write,
+    mut ty: Pin<&mut lldb::SBType>,
+    depth: usize,
+    in_variant: bool,
+) -> anyhow::Result<()> {
+    let mut members = Vec::new();
+
+    for i in 0..ty.as_mut().GetNumberOfFields() {
+        moveit!{
+            let mut member = ty.as_mut().GetFieldAtIndex(i);
+            let mut member_ty = member.as_mut().GetType();
+        }
+
+        let name = cstr!(unsafe member.as_mut().GetName())
+            .map(|name| Vec::from(name.to_bytes()))
+            .unwrap_or_default();
+        let ty_name = cstr!(unsafe member_ty.as_mut().GetName())
+            .map(|name| Vec::from(name.to_bytes()))
+            .unwrap_or_default();
+
+        members.push(Member {
+            index: i,
+            offset: member.as_mut().GetOffsetInBytes(),
+            size: member_ty.as_mut().GetByteSize(),
+            // lldb wraps rust enum variants as `$variants$.$variant$N.value`
+            is_wrapper: name.starts_with(b"$") || (in_variant && name == b"value"),
+            is_variant: is_variant_name(&ty_name),
+            name,
+            ty: ty_name,
+        });
+    }
+
+    members.sort_by_key(|member| std::cmp::Reverse(member.size));
+
+    for member in members.iter() {
+        let indent = "  ".repeat(depth);
+
+        if !member.is_wrapper {
+            writeln!(
+                stdout,
+                "{}+{:#06x} [{}] {}: {:?}",
+                indent,
+                member.offset,
+                member.size,
+                member.name.as_bstr(),
+                member.ty.as_bstr()
+            )?;
+        }
+
+        if member.is_wrapper || member.is_variant {
+            moveit!{
+                let mut child = ty.as_mut().GetFieldAtIndex(member.index);
+                let mut child_ty = child.as_mut().GetType();
+            }
+
+            let depth = if member.is_wrapper { depth } else { depth + 1 };
+            print_members(stdout, child_ty.as_mut(), depth, member.name.starts_with(b"$variant$"))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn is_async_env(name: &[u8]) -> bool {
+    ASYNC_PATTERNS.iter().any(|pattern| name.find(pattern).is_some())
+}
+
+/// Generator variants are named `Unresumed`, `Returned`, `Panicked` and `SuspendN`.
+pub fn is_variant_name(ty_name: &[u8]) -> bool {
+    let name = match ty_name.rfind("::") {
+        Some(pos) => &ty_name[pos + 2..],
+        None => ty_name
+    };
+
+    match name.strip_prefix(b"Suspend") {
+        Some(n) => !n.is_empty() && n.iter().all(u8::is_ascii_digit),
+        None => matches!(name, b"Unresumed" | b"Returned" | b"Panicked")
+    }
+}
+
+#[test]
+fn test_is_variant_name() {
+    assert!(is_async_env(b"app::main::{async_fn_env#0}"));
+    assert!(is_async_env(b"app::run::{async_block_env#1}<u8>"));
+    assert!(!is_async_env(b"app::main::{closure_env#0}"));
+
+    assert!(is_variant_name(b"app::main::{async_fn_env#0}::Suspend0"));
+    assert!(is_variant_name(b"Unresumed"));
+    assert!(is_variant_name(b"Returned"));
+    assert!(!is_variant_name(b"Suspend"));
+    assert!(!is_variant_name(b"Suspendx"));
+    assert!(!is_variant_name(b"app::Foo"));
+}
@@ -1,16 +1,29 @@
 #[macro_use]
 mod util;
 mod sys;
+mod procfs;
 mod search;
 mod read;
 mod thread;
 mod trace;
+mod dump;
+mod write;
+mod string;
+mod walk;
+mod watch;
+mod stacks;
+mod locks;
+mod profile;
+mod futures;
 
 use std::pin::Pin;
 
 
 macro_rules! command {
     ( $sym:ident = $cmd:ident ) => {
+        command!($sym = $cmd, stringify!($cmd));
+    };
+    ( $sym:ident = $cmd:ident, $name:expr ) => {
         #[no_mangle]
         pub unsafe extern "C" fn $sym(
             debugger: *mut libc::c_void,
@@ -20,7 +33,7 @@ macro_rules! command {
             let debugger =
                 Pin::new_unchecked(&mut *(debugger as *mut sys::lldb::SBDebugger));
 
-            let cmd = match util::command_from_ptr::<$cmd::Command>(stringify!($cmd), command) {
+            let cmd = match util::command_from_ptr::<$cmd::Command>($name, command) {
                 Ok(cmd) => cmd,
                 Err(output) => {
                     println!("{}", output);
@@ -31,7 +44,7 @@ macro_rules! command {
             match cmd.execute(debugger) {
                 Ok(()) => true,
                 Err(err) => {
-                    println!("{} failed: {:?}", stringify!($cmd), err);
+                    println!("{} failed: {:?}", $name, err);
                     false
                 }
             }
@@ -43,3 +56,12 @@ command!(mydbg_search_do_execute = search);
 command!(mydbg_read_do_execute = read);
 command!(mydbg_thread_do_execute = thread);
 command!(mydbg_trace_do_execute = trace);
+command!(mydbg_dump_do_execute = dump);
+command!(mydbg_write_do_execute = write);
+command!(mydbg_str_do_execute = string, "str");
+command!(mydbg_walk_do_execute = walk);
+command!(mydbg_watch_do_execute = watch, "watch-read");
+command!(mydbg_stacks_do_execute = stacks);
+command!(mydbg_locks_do_execute = locks);
+command!(mydbg_profile_do_execute = profile);
+command!(mydbg_futures_do_execute = futures);
@@ -0,0 +1,306 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::collections::HashMap;
+use argh::FromArgs;
+use autocxx::moveit::moveit;
+use bstr::ByteSlice;
+use crate::sys::lldb;
+use crate::util::read_memory;
+
+
+/// MyDbg Locks command
+#[derive(FromArgs)]
+pub struct Command {
+    //
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LockKind {
+    Pthread,
+    Futex,
+    RustStd,
+    ParkingLot,
+}
+
+/// Function name patterns of blocking lock paths, first match wins.
+const LOCK_PATTERNS: &[(&str, LockKind)] = &[
+    ("pthread_mutex_lock", LockKind::Pthread),
+    ("pthread_mutex_timedlock", LockKind::Pthread),
+    ("__lll_lock_wait", LockKind::Pthread),
+    ("futex::Mutex::lock_contended", LockKind::RustStd),
+    ("futex_mutex::Mutex::lock_contended", LockKind::RustStd),
+    ("parking_lot::raw_mutex::RawMutex::lock_slow", LockKind::ParkingLot),
+    ("parking_lot::raw_rwlock::RawRwLock::lock_", LockKind::ParkingLot),
+    ("futex_wait", LockKind::Futex),
+    ("__futex_abstimed_wait", LockKind::Futex),
+];
+
+/// Callers of a futex wait that park idle threads rather than wait for a lock.
+const IDLE_PATTERNS: &[&str] = &[
+    "pthread_cond_wait",
+    "pthread_cond_timedwait",
+    "pthread_cond_clockwait",
+    "Condvar::wait",
+    "thread::park",
+    "parking_lot::condvar::Condvar::wait",
+];
+
+struct Blocked {
+    index: u32,
+    tid: u64,
+    name: Vec<u8>,
+    kind: LockKind,
+    frame: u32,
+    function: Vec<u8>,
+    mutex: Option<u64>,
+    owner: Option<u64>,
+}
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let mut error = lldb::SBError::new();
+        }
+
+        let triple = cstr!(unsafe target.as_mut().GetTriple())
+            .map(|triple| Vec::from(triple.to_bytes()))
+            .unwrap_or_default();
+        let futex_reg = if triple.starts_with(b"aarch64") {
+            c"x0"
+        } else {
+            c"rdi"
+        };
+
+        let mut buf = Vec::new();
+        let mut blocked = Vec::new();
+        let mut idle = Vec::new();
+        let mut names = HashMap::new();
+
+        let threads = process.as_mut().GetNumThreads() as usize;
+        for thread_idx in 0..threads {
+            moveit!(let mut thread = process.as_mut().GetThreadAtIndex(thread_idx));
+
+            let thread_name = cstr!(unsafe thread.GetName())
+                .map(|name| Vec::from(name.to_bytes()))
+                .unwrap_or_default();
+            names.insert(thread.GetThreadID(), (thread.GetIndexID(), thread_name.clone()));
+
+            let frames = thread.as_mut().GetNumFrames();
+            let mut found = None;
+            let mut is_idle = false;
+
+            for frame_idx in 0..frames {
+                moveit!(let mut frame = thread.as_mut().GetFrameAtIndex(frame_idx));
+
+                let function = match cstr!(unsafe frame.as_mut().GetFunctionName()) {
+                    Some(function) => function.to_bytes(),
+                    None => continue
+                };
+
+                if IDLE_PATTERNS.iter().any(|pattern| function.find(pattern).is_some()) {
+                    is_idle = true;
+                }
+
+                let kind = LOCK_PATTERNS.iter()
+                    .find(|(pattern, _)| function.find(pattern).is_some())
+                    .map(|(_, kind)| *kind);
+
+                if let Some(kind) = kind {
+                    let mutex = first_pointer_argument(frame.as_mut());
+                    found = Some((kind, frame_idx, function.to_vec(), mutex));
+
+                    // futex wait is usually below the real lock frame
+                    if kind != LockKind::Futex {
+                        break
+                    }
+                }
+            }
+
+            let (kind, frame_idx, function, mutex) = match found {
+                Some(found) => found,
+                None => continue
+            };
+
+            // a bare futex wait under a condvar or park is an idle thread, not a lock
+            if kind == LockKind::Futex && is_idle {
+                idle.push((thread.GetIndexID(), thread.GetThreadID(), thread_name));
+                continue
+            }
+
+            // fallback to the futex address of the syscall in the innermost frame
+            let mutex = mutex.or_else(|| {
+                moveit!{
+                    let mut frame = thread.as_mut().GetFrameAtIndex(0);
+                    let mut reg = unsafe { frame.as_mut().FindRegister(futex_reg.as_ptr()) };
+                }
+
+                Some(reg.as_mut().GetValueAsUnsigned1(0)).filter(|&addr| addr != 0)
+            });
+
+            // glibc `pthread_mutex_t`: `{ int __lock; unsigned __count; int __owner; ... }`
+            let owner = match (kind, mutex) {
+                (LockKind::Pthread, Some(mutex)) => read_memory(process.as_mut(), &mut buf, mutex + 8, 4, error.as_mut())
+                    .ok()
+                    .map(|owner| i32::from_le_bytes(owner.try_into().unwrap()))
+                    .filter(|&owner| owner > 0)
+                    .map(|owner| owner as u64),
+                _ => None
+            };
+
+            blocked.push(Blocked {
+                index: thread.GetIndexID(),
+                tid: thread.GetThreadID(),
+                name: thread_name,
+                kind,
+                frame: frame_idx,
+                function,
+                mutex,
+                owner
+            });
+        }
+
+        for item in blocked.iter() {
+            write!(
+                stdout,
+                "thread #{} tid= {} {:?} blocked in {:?} frame #{} {:?}",
+                item.index,
+                item.tid,
+                item.name.as_bstr(),
+                item.kind,
+                item.frame,
+                item.function.as_bstr()
+            )?;
+
+            match item.mutex {
+                Some(mutex) => write!(stdout, " lock= {:018p}", mutex as *const u8)?,
+                None => write!(stdout, " lock= unknown")?
+            }
+
+            match item.owner {
+                Some(owner) => match names.get(&owner) {
+                    Some((index, name)) => write!(stdout, " owner= tid {} (thread #{} {:?})", owner, index, name.as_bstr())?,
+                    None => write!(stdout, " owner= tid {} (not in process?)", owner)?
+                },
+                None => write!(stdout, " owner= unknown")?
+            }
+
+            writeln!(stdout)?;
+        }
+
+        let edges = blocked.iter()
+            .filter_map(|item| item.owner.map(|owner| (item.tid, owner)))
+            .collect::<HashMap<_, _>>();
+        let cycles = find_cycles(&edges);
+
+        if !idle.is_empty() {
+            writeln!(stdout)?;
+            write!(stdout, "{} threads waiting on condvar or park:", idle.len())?;
+            for (i, (index, tid, name)) in idle.iter().enumerate() {
+                let sep = if i == 0 { "" } else { "," };
+                write!(stdout, "{} #{} tid= {} {:?}", sep, index, tid, name.as_bstr())?;
+            }
+            writeln!(stdout)?;
+        }
+
+        writeln!(stdout)?;
+        writeln!(stdout, "{} threads blocked on locks", blocked.len())?;
+
+        if blocked.iter().any(|item| matches!(item.kind, LockKind::RustStd | LockKind::ParkingLot)) {
+            writeln!(stdout, "note: Rust std and parking_lot mutexes do not record an owner, they never appear in a deadlock cycle")?;
+        }
+
+        for cycle in cycles.iter() {
+            write!(stdout, "deadlock:")?;
+            for tid in cycle.iter() {
+                write!(stdout, " tid {} ->", tid)?;
+            }
+            writeln!(stdout, " tid {}", cycle[0])?;
+        }
+
+        if cycles.is_empty() {
+            writeln!(stdout, "no deadlock cycle found")?;
+        }
+
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+fn first_pointer_argument(frame: Pin<&mut lldb::SBFrame>) -> Option<u64> {
+    moveit!{
+        let arguments = frame.GetVariables(
+            true,
+            false,
+            false,
+            true
+        );
+    }
+
+    let count = arguments.GetSize();
+    for i in 0..count {
+        moveit!{
+            let mut value = arguments.GetValueAtIndex(i);
+            let mut ty = value.as_mut().GetType();
+        }
+
+        if ty.as_mut().IsPointerType() || ty.as_mut().IsReferenceType() {
+            let addr = value.as_mut().GetValueAsUnsigned1(0);
+            if addr != 0 {
+                return Some(addr);
+            }
+        }
+    }
+
+    None
+}
+
+/// Find cycles in wait-for graph, each thread waits for at most one owner.
+pub fn find_cycles(edges: &HashMap<u64, u64>) -> Vec<Vec<u64>> {
+    let mut cycles = Vec::new();
+    let mut done = std::collections::HashSet::new();
+
+    let mut starts = edges.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable();
+
+    for start in starts {
+        let mut path = Vec::new();
+        let mut node = start;
+
+        while !done.contains(&node) {
+            if let Some(pos) = path.iter().position(|&n| n == node) {
+                cycles.push(path[pos..].to_vec());
+                break
+            }
+
+            path.push(node);
+
+            match edges.get(&node) {
+                Some(&next) => node = next,
+                None => break
+            }
+        }
+
+        done.extend(path);
+    }
+
+    cycles
+}
+
+#[test]
+fn test_find_cycles() {
+    let edges = [(1, 2), (2, 3), (3, 1), (4, 1), (5, 6)]
+        .into_iter()
+        .collect::<HashMap<u64, u64>>();
+    assert_eq!(find_cycles(&edges), vec![vec![1, 2, 3]]);
+
+    let edges = [(1, 1)].into_iter().collect::<HashMap<u64, u64>>();
+    assert_eq!(find_cycles(&edges), vec![vec![1]]);
+
+    let edges = [(1, 2), (2, 3)].into_iter().collect::<HashMap<u64, u64>>();
+    assert!(find_cycles(&edges).is_empty());
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::path::PathBuf;
+
+
+/// Kernel view of a thread, from `/proc/<pid>/task/<tid>`.
+pub struct TaskInfo {
+    pub state: char,
+    /// user time in seconds
+    pub utime: f64,
+    /// system time in seconds
+    pub stime: f64,
+    pub processor: Option<i64>,
+    pub voluntary_switches: Option<u64>,
+    pub involuntary_switches: Option<u64>,
+    pub wchan: Option<String>,
+    /// milliseconds
+    pub sum_exec_runtime: Option<f64>,
+}
+
+pub struct Stat {
+    pub state: char,
+    pub utime: u64,
+    pub stime: u64,
+    pub processor: Option<i64>,
+}
+
+pub fn task_dir(pid: u64, tid: u64) -> PathBuf {
+    PathBuf::from(format!("/proc/{}/task/{}", pid, tid))
+}
+
+pub fn read_task(pid: u64, tid: u64) -> anyhow::Result<TaskInfo> {
+    use anyhow::Context;
+
+    let dir = task_dir(pid, tid);
+
+    let stat = std::fs::read_to_string(dir.join("stat"))
+        .with_context(|| format!("read {:?} failed", dir))?;
+    let stat = parse_stat(&stat).context("bad stat")?;
+
+    let status = std::fs::read_to_string(dir.join("status")).unwrap_or_default();
+    let sched = std::fs::read_to_string(dir.join("sched")).unwrap_or_default();
+    let wchan = std::fs::read_to_string(dir.join("wchan")).ok()
+        .map(|wchan| wchan.trim().to_owned())
+        .filter(|wchan| !wchan.is_empty() && wchan != "0");
+
+    // # Safety
+    //
+    // sysconf has no side effect
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    let ticks = if ticks > 0 { ticks as f64 } else { 100.0 };
+
+    Ok(TaskInfo {
+        state: stat.state,
+        utime: stat.utime as f64 / ticks,
+        stime: stat.stime as f64 / ticks,
+        processor: stat.processor,
+        voluntary_switches: find_field(&status, "voluntary_ctxt_switches:")
+            .and_then(|value| value.parse().ok()),
+        involuntary_switches: find_field(&status, "nonvoluntary_ctxt_switches:")
+            .and_then(|value| value.parse().ok()),
+        wchan,
+        sum_exec_runtime: find_field(&sched, "se.sum_exec_runtime")
+            .and_then(|value| value.trim_start_matches(':').trim().parse().ok()),
+    })
+}
+
+/// Parse `/proc/<pid>/task/<tid>/stat`, comm may contain spaces and `)`.
+pub fn parse_stat(stat: &str) -> Option<Stat> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    let fields = rest.split_whitespace().collect::<Vec<_>>();
+
+    // fields[0] is field 3 in proc(5)
+    Some(Stat {
+        state: fields.first()?.chars().next()?,
+        utime: fields.get(11)?.parse().ok()?,
+        stime: fields.get(12)?.parse().ok()?,
+        processor: fields.get(36).and_then(|cpu| cpu.parse().ok()),
+    })
+}
+
+/// Soft `RLIMIT_STACK` of a process, `None` if unlimited or unknown.
+pub fn read_stack_limit(pid: u64) -> Option<u64> {
+    let limits = std::fs::read_to_string(format!("/proc/{}/limits", pid)).ok()?;
+    parse_stack_limit(&limits)
+}
+
+/// Parse the `Max stack size` row of `/proc/<pid>/limits`.
+pub fn parse_stack_limit(limits: &str) -> Option<u64> {
+    let soft = find_field(limits, "Max stack size")?
+        .split_whitespace()
+        .next()?;
+    soft.parse().ok()
+}
+
+fn find_field<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    text.lines()
+        .find_map(|line| line.strip_prefix(key))
+        .map(str::trim)
+}
+
+impl fmt::Display for TaskInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "state= {} cpu= {:.2}s (user {:.2}s sys {:.2}s)",
+            self.state,
+            self.utime + self.stime,
+            self.utime,
+            self.stime
+        )?;
+
+        if let (Some(voluntary), Some(involuntary)) = (self.voluntary_switches, self.involuntary_switches) {
+            write!(f, " ctxt= {}/{}", voluntary, involuntary)?;
+        }
+
+        if let Some(runtime) = self.sum_exec_runtime {
+            write!(f, " runtime= {:.1}ms", runtime)?;
+        }
+
+        if let Some(cpu) = self.processor {
+            write!(f, " last_cpu= {}", cpu)?;
+        }
+
+        if let Some(wchan) = self.wchan.as_ref() {
+            write!(f, " wchan= {}", wchan)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_stat() {
+    let stat = "1234 (tokio (worker) 1) S 1 1234 1234 0 -1 4194560 100 0 0 0 \
+        250 30 0 0 20 0 12 0 100 1000000 200 18446744073709551615 \
+        1 1 0 0 0 0 0 4096 0 0 0 0 17 3 0 0 0 0 0";
+    let stat = parse_stat(stat).unwrap();
+
+    assert_eq!(stat.state, 'S');
+    assert_eq!(stat.utime, 250);
+    assert_eq!(stat.stime, 30);
+    assert_eq!(stat.processor, Some(3));
+}
+
+#[test]
+fn test_parse_stack_limit() {
+    let limits = "Limit                     Soft Limit           Hard Limit           Units     \n\
+        Max cpu time              unlimited            unlimited            seconds   \n\
+        Max stack size            8388608              unlimited            bytes     \n";
+    assert_eq!(parse_stack_limit(limits), Some(8388608));
+
+    let limits = "Max stack size            unlimited            unlimited            bytes     \n";
+    assert_eq!(parse_stack_limit(limits), None);
+}
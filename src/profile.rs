@@ -0,0 +1,145 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::path::PathBuf;
+use std::time::{ Duration, Instant };
+use std::collections::HashMap;
+use argh::FromArgs;
+use anyhow::Context;
+use autocxx::moveit::moveit;
+use crate::sys::lldb;
+use crate::stacks::backtrace;
+use crate::util::{ HumanDuration, wait_for_stop };
+
+
+/// MyDbg Profile command
+#[derive(FromArgs)]
+pub struct Command {
+    /// sampling duration, default 10s
+    #[argh(option, default = "HumanDuration(Duration::from_secs(10))")]
+    duration: HumanDuration,
+
+    /// samples per second, default 50
+    #[argh(option, default = "50")]
+    hz: u32,
+
+    /// folded stacks output file
+    #[argh(option, short = 'o')]
+    out: PathBuf,
+}
+
+impl Command {
+    pub fn execute(self, mut debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        anyhow::ensure!(self.hz != 0, "hz must be greater than 0");
+
+        moveit!{
+            let mut target = debugger.as_mut().GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let broadcast = process.as_ref().GetBroadcaster();
+            let mut listener = unsafe { lldb::SBListener::new1(c"mydbg-profile".as_ptr()) };
+            let mut event = lldb::SBEvent::new();
+        }
+
+        match process.as_mut().GetState() {
+            lldb::StateType::eStateStopped => (),
+            state => anyhow::bail!("bad state: {:?}", state as u32)
+        }
+
+        // eBroadcastBitStateChanged
+        listener.as_mut().StartListeningForEvents(&broadcast, 1 << 0);
+
+        let is_async = debugger.as_mut().GetAsync();
+        debugger.as_mut().SetAsync(true);
+
+        let ret = sample(
+            process.as_mut(),
+            listener.as_mut(),
+            event.as_mut(),
+            self.duration.0,
+            Duration::from_secs(1) / self.hz
+        );
+
+        debugger.as_mut().SetAsync(is_async);
+        listener.as_mut().StopListeningForEvents(&broadcast, 1 << 0);
+
+        let (folded, samples) = ret?;
+
+        let mut folded = folded.into_iter().collect::<Vec<_>>();
+        folded.sort();
+
+        let mut output = io::BufWriter::new(
+            std::fs::File::create(&self.out)
+                .with_context(|| format!("create file failed: {:?}", self.out))?
+        );
+        for (stack, count) in folded.iter() {
+            output.write_all(stack)?;
+            writeln!(output, " {}", count)?;
+        }
+        output.flush()?;
+
+        println!("{} samples, {} unique stacks, write to {:?}", samples, folded.len(), self.out);
+
+        Ok(())
+    }
+}
+
+/// Interrupt the process every `interval`, collect all thread backtraces and resume,
+/// the process is left stopped.
+fn sample(
+    mut process: Pin<&mut lldb::SBProcess>,
+    mut listener: Pin<&mut lldb::SBListener>,
+    mut event: Pin<&mut lldb::SBEvent>,
+    duration: Duration,
+    interval: Duration,
+) -> anyhow::Result<(HashMap<Vec<u8>, u64>, u64)> {
+    let mut folded: HashMap<Vec<u8>, u64> = HashMap::new();
+    let mut samples = 0;
+    let deadline = Instant::now() + duration;
+
+    while Instant::now() < deadline {
+        moveit!(let err = process.as_mut().Continue());
+        if !err.Success() {
+            let err_msg = cstr!(unsafe err.GetCString());
+            anyhow::bail!("continue failed: {:?}", err_msg);
+        }
+
+        std::thread::sleep(interval);
+
+        moveit!(let err = process.as_mut().Stop());
+        if !err.Success() {
+            let err_msg = cstr!(unsafe err.GetCString());
+            anyhow::bail!("stop failed: {:?}", err_msg);
+        }
+
+        match wait_for_stop(listener.as_mut(), event.as_mut(), Duration::from_secs(10))? {
+            lldb::StateType::eStateStopped | lldb::StateType::eStateSuspended => (),
+            state => {
+                println!("process is gone: {:?}", state as u32);
+                break
+            }
+        }
+
+        let threads = process.as_mut().GetNumThreads() as usize;
+        for thread_idx in 0..threads {
+            moveit!(let mut thread = process.as_mut().GetThreadAtIndex(thread_idx));
+
+            // `;` separates frames in folded stacks
+            let escape = |b: &u8| if *b == b';' { b',' } else { *b };
+
+            let mut stack = match cstr!(unsafe thread.GetName()) {
+                Some(name) => name.to_bytes().iter().map(escape).collect(),
+                None => format!("thread-{}", thread.GetThreadID()).into_bytes()
+            };
+
+            for frame in backtrace(thread.as_mut()).iter().rev() {
+                stack.push(b';');
+                stack.extend(frame.iter().map(escape));
+            }
+
+            *folded.entry(stack).or_default() += 1;
+        }
+
+        samples += 1;
+    }
+
+    Ok((folded, samples))
+}
@@ -1,19 +1,26 @@
 use std::io::{ self, Write };
 use std::pin::Pin;
 use std::path::PathBuf;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::{ Mutex, LazyLock };
 use argh::FromArgs;
 use anyhow::Context;
 use autocxx::moveit::moveit;
+use bstr::ByteSlice;
 use crate::sys::lldb;
-use crate::util::{ print_pretty_bytes, read_memory, u64ptr };
+use crate::util::{
+    HexDump, diff_ranges, memory_regions, print_instructions,
+    read_memory, dump_memory, u64ptr
+};
 
 
 /// MyDbg Read command
 #[derive(FromArgs)]
 pub struct Command {
-    /// read address
+    /// read address, default is the address of diff snapshot
     #[argh(positional)]
-    address: String,
+    address: Option<String>,
 
     /// read size, default 64
     #[argh(option, short = 's')]
@@ -21,13 +28,79 @@ pub struct Command {
 
     /// read bytes to output file
     #[argh(option, short = 'o')]
-    output: Option<PathBuf>
+    output: Option<PathBuf>,
+
+    /// save bytes as named snapshot
+    #[argh(option)]
+    save: Option<String>,
+
+    /// diff bytes against snapshot or file
+    #[argh(option)]
+    diff: Option<String>,
+
+    /// hexdump bytes per line, 8/16/32, default 16
+    #[argh(option)]
+    width: Option<usize>,
+
+    /// hexdump bytes per group, 1/2/4/8, default 1
+    #[argh(option)]
+    group: Option<usize>,
+
+    /// overlay type on memory, e.g. 'struct Session'
+    #[argh(option, short = 't', long = "type")]
+    ty: Option<String>,
+
+    /// show instructions, default for executable region without --width or --group
+    #[argh(switch)]
+    disasm: bool,
 }
 
+struct Snapshot {
+    addr: Option<u64>,
+    bytes: Vec<u8>,
+}
+
+static SNAPSHOTS: LazyLock<Mutex<HashMap<String, Snapshot>>> = LazyLock::new(Default::default);
+
 impl Command {
     pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
-        let addr = u64ptr(self.address.as_str())?;
-        let size = self.size.unwrap_or(64);
+        // snapshots only work with the hexdump output
+        anyhow::ensure!(
+            (self.save.is_none() && self.diff.is_none()) || (self.ty.is_none() && self.output.is_none()),
+            "--save and --diff can not be used with --type or --output"
+        );
+        anyhow::ensure!(
+            !self.disasm || (self.ty.is_none() && self.output.is_none()),
+            "--disasm can not be used with --type or --output"
+        );
+
+        let mut snapshots = SNAPSHOTS.lock().unwrap();
+
+        let old = match self.diff.as_ref() {
+            Some(name) => match snapshots.get(name) {
+                Some(snapshot) => Some(Snapshot {
+                    addr: snapshot.addr,
+                    bytes: snapshot.bytes.clone()
+                }),
+                None => Some(Snapshot {
+                    addr: None,
+                    bytes: std::fs::read(name)
+                        .with_context(|| format!("no snapshot or file: {:?}", name))?
+                })
+            },
+            None => None
+        };
+
+        let addr = match (self.address.as_ref(), old.as_ref().and_then(|old| old.addr)) {
+            (Some(addr), _) => u64ptr(addr.as_str())?,
+            (None, Some(addr)) => addr,
+            (None, None) => anyhow::bail!("need address")
+        };
+        let size = self.size
+            .or(old.as_ref().map(|old| old.bytes.len()))
+            .unwrap_or(64);
+
+        let color = debugger.GetUseColor();
 
         moveit!{
             let mut target = debugger.GetSelectedTarget();
@@ -37,25 +110,34 @@ impl Command {
 
         let mut buf: Vec<u8> = Vec::new();
 
-        if let Some(path) = self.output {
-            let mut output = std::fs::File::create(&path)?;
-
-            const CHUNK_SIZE: usize = 16 * 1024;
+        if let Some(ty) = self.ty {
+            let regions = if color {
+                memory_regions(process.as_mut())
+            } else {
+                Vec::new()
+            };
+            let dump = HexDump {
+                width: self.width.unwrap_or(16),
+                group: self.group.unwrap_or(1),
+                color,
+                regions: &regions,
+                ..HexDump::default()
+            };
 
-            for offset in (0..size).step_by(CHUNK_SIZE) {
-                let addr = addr + offset as u64;
-                let size = std::cmp::min(size - offset, CHUNK_SIZE);
-
-                let buf = read_memory(
-                    process.as_mut(),
-                    &mut buf,
-                    addr + offset as u64,
-                    size,
-                    error.as_mut()
-                ).with_context(|| format!("addr={:p},size={}", addr as *const u8, size))?;
+            let mut stdout = io::stdout().lock();
+            print_type_overlay(&mut stdout, target.as_mut(), addr, &ty, &dump)?;
+            stdout.flush()?;
+        } else if let Some(path) = self.output {
+            let mut output = std::fs::File::create(&path)?;
 
-                output.write_all(buf)?;
-            }
+            dump_memory(
+                process.as_mut(),
+                &mut buf,
+                addr,
+                size,
+                error.as_mut(),
+                &mut output
+            )?;
 
             output.flush()?;
         } else {
@@ -68,10 +150,228 @@ impl Command {
             )?;
 
             let mut stdout = io::stdout().lock();
-            print_pretty_bytes(&mut stdout, addr, buf)?;
+
+            let regions = memory_regions(process.as_mut());
+            // asking for a hexdump layout means hexdump
+            let executable = self.width.is_none() && self.group.is_none() && regions.iter()
+                .any(|region| region.range.contains(&addr) && region.executable);
+            let dump = HexDump {
+                width: self.width.unwrap_or(16),
+                group: self.group.unwrap_or(1),
+                color,
+                regions: if color { &regions[..] } else { &[] },
+                ..HexDump::default()
+            };
+
+            if (self.disasm || executable) && old.is_none() {
+                print_instructions(&mut stdout, target.as_mut(), addr, buf)?;
+            } else if let Some(old) = old {
+                let ranges = diff_ranges(&old.bytes, buf);
+
+                HexDump {
+                    highlight: &|i| ranges.iter().any(|range| range.contains(&i)),
+                    ..dump
+                }.print(&mut stdout, addr, buf)?;
+                writeln!(stdout)?;
+
+                if old.bytes.len() != buf.len() {
+                    writeln!(stdout, "size changed: {} -> {}", old.bytes.len(), buf.len())?;
+                }
+
+                for range in ranges.iter() {
+                    writeln!(
+                        stdout,
+                        "changed [{:018p}-{:018p}] {} bytes",
+                        (addr + range.start as u64) as *const u8,
+                        (addr + range.end as u64) as *const u8,
+                        range.len()
+                    )?;
+                }
+
+                if ranges.is_empty() {
+                    writeln!(stdout, "no change")?;
+                }
+            } else {
+                dump.print(&mut stdout, addr, buf)?;
+            }
+
             stdout.flush()?;
+
+            if let Some(name) = self.save {
+                snapshots.insert(name, Snapshot {
+                    addr: Some(addr),
+                    bytes: buf.to_vec()
+                });
+            }
         }
 
         Ok(())
     }
 }
+
+fn strip_type_keyword(type_name: &str) -> &str {
+    ["struct ", "class ", "union ", "enum "]
+        .iter()
+        .fold(type_name.trim(), |name, kw| name.strip_prefix(kw).unwrap_or(name))
+        .trim()
+}
+
+pub fn type_size(target: Pin<&mut lldb::SBTarget>, type_name: &str) -> anyhow::Result<usize> {
+    let name = strip_type_keyword(type_name);
+    let cname = CString::new(name)?;
+
+    moveit!{
+        let mut ty = unsafe { target.FindFirstType(cname.as_ptr()) };
+    }
+
+    anyhow::ensure!(ty.as_mut().IsValid(), "type not found: {:?}", name);
+
+    Ok(ty.as_mut().GetByteSize().try_into()?)
+}
+
+struct Field {
+    offset: usize,
+    size: usize,
+    bits: Option<u32>,
+    name: Vec<u8>,
+    ty: Vec<u8>,
+    value: Option<Vec<u8>>,
+}
+
+/// Print memory at `addr` as `type_name`, one hexdump per field,
+/// including padding bytes.
+pub fn print_type_overlay(
+    stdout: &mut dyn Write,
+    mut target: Pin<&mut lldb::SBTarget>,
+    addr: u64,
+    type_name: &str,
+    dump: &HexDump,
+) -> anyhow::Result<()> {
+    let name = strip_type_keyword(type_name);
+    let cname = CString::new(name)?;
+
+    moveit!{
+        let mut ty = unsafe { target.as_mut().FindFirstType(cname.as_ptr()) };
+        let mut process = target.as_mut().GetProcess();
+        let address = target.as_mut().ResolveLoadAddress(addr);
+        let mut error = lldb::SBError::new();
+    }
+
+    anyhow::ensure!(ty.as_mut().IsValid(), "type not found: {:?}", name);
+
+    let size: usize = ty.as_mut().GetByteSize().try_into()?;
+    let mut buf = Vec::new();
+    let bytes = read_memory(process.as_mut(), &mut buf, addr, size, error.as_mut())?;
+
+    moveit!{
+        let mut value = unsafe {
+            target.as_mut().CreateValueFromAddress(c"overlay".as_ptr(), &*address, &*ty)
+        };
+    }
+
+    let mut fields = Vec::new();
+
+    for i in 0..ty.as_mut().GetNumberOfDirectBaseClasses() {
+        moveit!{
+            let mut member = ty.as_mut().GetDirectBaseClassAtIndex(i);
+            let mut member_ty = member.as_mut().GetType();
+        }
+
+        fields.push(Field {
+            offset: member.as_mut().GetOffsetInBytes().try_into()?,
+            size: member_ty.as_mut().GetByteSize().try_into()?,
+            bits: None,
+            name: b"<base>".to_vec(),
+            ty: cstr!(unsafe member_ty.as_mut().GetName())
+                .map(|name| Vec::from(name.to_bytes()))
+                .unwrap_or_default(),
+            value: None
+        });
+    }
+
+    for i in 0..ty.as_mut().GetNumberOfFields() {
+        moveit!{
+            let mut member = ty.as_mut().GetFieldAtIndex(i);
+            let mut member_ty = member.as_mut().GetType();
+        }
+
+        let name = cstr!(unsafe member.as_mut().GetName())
+            .map(|name| Vec::from(name.to_bytes()))
+            .unwrap_or_default();
+
+        let (offset, size, bits) = if member.as_mut().IsBitfield() {
+            let bit_offset = member.as_mut().GetOffsetInBits();
+            let bits = member.as_mut().GetBitfieldSizeInBits();
+            let size = (bit_offset % 8 + bits as u64).div_ceil(8);
+            (bit_offset / 8, size, Some(bits))
+        } else {
+            (member.as_mut().GetOffsetInBytes(), member_ty.as_mut().GetByteSize(), None)
+        };
+
+        let field_value = if !name.is_empty() {
+            let cname = CString::new(name.clone())?;
+
+            moveit!{
+                let mut child = unsafe { value.as_mut().GetChildMemberWithName(cname.as_ptr()) };
+            }
+
+            cstr!(unsafe child.as_mut().GetValue())
+                .or_else(|| cstr!(unsafe child.as_mut().GetSummary()))
+                .map(|value| Vec::from(value.to_bytes()))
+        } else {
+            None
+        };
+
+        fields.push(Field {
+            offset: offset.try_into()?,
+            size: size.try_into()?,
+            bits,
+            name,
+            ty: cstr!(unsafe member_ty.as_mut().GetName())
+                .map(|name| Vec::from(name.to_bytes()))
+                .unwrap_or_default(),
+            value: field_value
+        });
+    }
+
+    fields.sort_by_key(|field| field.offset);
+
+    writeln!(stdout, "{} size= {} at {:018p}", name, size, addr as *const u8)?;
+
+    let mut covered = 0;
+
+    let print_padding = |stdout: &mut dyn Write, range: std::ops::Range<usize>| -> anyhow::Result<()> {
+        writeln!(stdout, "+{:#06x} [{}] <padding>", range.start, range.len())?;
+        dump.print(stdout, addr + range.start as u64, &bytes[range])?;
+        Ok(())
+    };
+
+    for field in fields.iter() {
+        let start = std::cmp::min(field.offset, size);
+        let end = std::cmp::min(start + field.size, size);
+
+        if start > covered {
+            print_padding(stdout, covered..start)?;
+        }
+
+        write!(stdout, "+{:#06x} [{}] {}", start, field.size, field.name.as_bstr())?;
+        if let Some(bits) = field.bits {
+            write!(stdout, ":{}", bits)?;
+        }
+        write!(stdout, ": {:?}", field.ty.as_bstr())?;
+        if let Some(value) = field.value.as_ref() {
+            write!(stdout, " = {}", value.as_bstr())?;
+        }
+        writeln!(stdout)?;
+
+        dump.print(stdout, addr + start as u64, &bytes[start..end])?;
+
+        covered = std::cmp::max(covered, end);
+    }
+
+    if covered < size {
+        print_padding(stdout, covered..size)?;
+    }
+
+    Ok(())
+}
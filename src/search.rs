@@ -6,7 +6,7 @@ use anyhow::Context;
 use autocxx::moveit::moveit;
 use bstr::ByteSlice;
 use crate::sys::lldb;
-use crate::util::{ print_pretty_bytes, read_memory, u64ptr };
+use crate::util::{ HexDump, frame_location, print_pretty_bytes, print_instructions, memory_regions, read_memory, u64ptr };
 
 
 /// MyDbg Search command
@@ -26,35 +26,46 @@ pub struct Command {
 
     /// search register only
     #[argh(switch)]
-    register_only: bool
+    register_only: bool,
+
+    /// hexdump bytes per line, 8/16/32, default 16
+    #[argh(option, default = "16")]
+    width: usize,
+
+    /// hexdump bytes per group, 1/2/4/8, default 1
+    #[argh(option, default = "1")]
+    group: usize,
 }
 
 impl Command {
     pub fn execute(self, mut debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
         let mut stdout = io::stdout().lock();
 
-        let value = if self.is_64bit_pointer {
-            Value::U64(u64ptr(self.value.as_str())?)
-        } else if self.is_hex {
-            let value = self.value.as_str();
-            if let Some(value) = value.strip_prefix("0x") {
-                let mut buf = data_encoding::HEXLOWER_PERMISSIVE.decode(value.as_bytes())
-                    .context("hex decode failed")?;
-                buf.reverse();
-                Value::Bytes(buf)
-            } else {
-                let buf = data_encoding::HEXLOWER_PERMISSIVE.decode(value.as_bytes())
-                    .context("hex decode failed")?;
-                Value::Bytes(buf)
-            }
-        } else {
-            Value::Bytes(self.value.into())
-        };
+        let value = Value::parse(self.value, self.is_hex, self.is_64bit_pointer)?;
 
         let thread_list = scan_threads_and_search_by_registers(&mut stdout, debugger.as_mut(), &value)?;
 
         if !self.register_only {
-            search_by_all_memory_region(&mut stdout, debugger, &value, &thread_list)?;
+            let color = debugger.GetUseColor();
+            let regions = if color {
+                moveit!{
+                    let mut target = debugger.as_mut().GetSelectedTarget();
+                    let mut process = target.as_mut().GetProcess();
+                }
+
+                memory_regions(process.as_mut())
+            } else {
+                Vec::new()
+            };
+            let dump = HexDump {
+                width: self.width,
+                group: self.group,
+                color,
+                regions: &regions,
+                ..HexDump::default()
+            };
+
+            search_by_all_memory_region(&mut stdout, debugger, &value, &thread_list, &dump)?;
         }
 
         stdout.flush()?;
@@ -95,6 +106,7 @@ pub fn scan_threads_and_search_by_registers(
         let thread_name = cstr!(unsafe thread.GetName())
             .map(|name| Vec::from(name.to_bytes()));
         let mut sp_range = None;
+        let mut inlined = Vec::new();
 
         let frames = thread.as_mut().GetNumFrames();
         for frame_idx in 0..frames {
@@ -110,7 +122,17 @@ pub fn scan_threads_and_search_by_registers(
             sp_range.start = std::cmp::min(sp_range.start, current_sp);
             sp_range.end = std::cmp::max(sp_range.end, current_sp);
 
+            // inlined frames share registers with their concrete frame,
+            // hits are reported on the concrete frame together with them
             if frame.IsInlined1() {
+                let mut name = cstr!(unsafe frame.as_mut().GetFunctionName())
+                    .map(|name| Vec::from(name.to_bytes()))
+                    .unwrap_or_default();
+                if let Some(location) = frame_location(frame.as_mut()) {
+                    name.extend_from_slice(b" at ");
+                    name.extend_from_slice(&location);
+                }
+                inlined.push((frame_idx, name));
                 continue
             }
 
@@ -170,6 +192,10 @@ pub fn scan_threads_and_search_by_registers(
                             reg_name,
                         )?;
 
+                        for (inlined_idx, name) in inlined.iter() {
+                            writeln!(stdout, "  inlined #{} {:?}", inlined_idx, name.as_bstr())?;
+                        }
+
                         if let Value::U64(v) = value {
                             buf.clear();
                             buf.extend_from_slice(&v.to_le_bytes());
@@ -180,6 +206,8 @@ pub fn scan_threads_and_search_by_registers(
                     }
                 }
             }
+
+            inlined.clear();
         }
 
         thread_list.push(Thread {
@@ -197,6 +225,7 @@ pub fn search_by_all_memory_region(
     debugger: Pin<&mut lldb::SBDebugger>,
     value: &Value,
     thread_list: &[Thread],
+    dump: &HexDump,
 ) -> anyhow::Result<()> {
     moveit!{
         let mut target = debugger.GetSelectedTarget();
@@ -291,7 +320,18 @@ pub fn search_by_all_memory_region(
             let show_end = std::cmp::min(show_end, buf.len());
             let show_addr_base = start_addr + show_start as u64;
 
-            print_pretty_bytes(stdout, show_addr_base, &buf[show_start..show_end])?;
+            let hit = (offset - show_start)..(offset - show_start + value.len());
+
+            HexDump {
+                highlight: &|i| hit.contains(&i),
+                ..*dump
+            }.print(stdout, show_addr_base, &buf[show_start..show_end])?;
+
+            if mem.as_mut().IsExecutable() {
+                writeln!(stdout)?;
+                print_instructions(stdout, target.as_mut(), addr, &buf[offset..show_end])?;
+            }
+
             writeln!(stdout)?;
         }
     }
@@ -300,6 +340,35 @@ pub fn search_by_all_memory_region(
 }
 
 impl Value {
+    pub fn parse(value: String, is_hex: bool, is_64bit_pointer: bool) -> anyhow::Result<Value> {
+        let value = if is_64bit_pointer {
+            Value::U64(u64ptr(value.as_str())?)
+        } else if is_hex {
+            let value = value.as_str();
+            if let Some(value) = value.strip_prefix("0x") {
+                let mut buf = data_encoding::HEXLOWER_PERMISSIVE.decode(value.as_bytes())
+                    .context("hex decode failed")?;
+                buf.reverse();
+                Value::Bytes(buf)
+            } else {
+                let buf = data_encoding::HEXLOWER_PERMISSIVE.decode(value.as_bytes())
+                    .context("hex decode failed")?;
+                Value::Bytes(buf)
+            }
+        } else {
+            Value::Bytes(value.into())
+        };
+
+        Ok(value)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Value::U64(v) => v.to_le_bytes().to_vec(),
+            Value::Bytes(v) => v.clone()
+        }
+    }
+
     pub fn len(&self) -> usize {
         match self {
             Value::U64(_) => std::mem::size_of::<u64>(),
@@ -0,0 +1,106 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::collections::HashMap;
+use argh::FromArgs;
+use autocxx::moveit::moveit;
+use bstr::ByteSlice;
+use crate::sys::lldb;
+
+
+/// MyDbg Stacks command
+#[derive(FromArgs)]
+pub struct Command {
+    /// only show stacks shared by at least N threads
+    #[argh(option, default = "1")]
+    min: usize,
+}
+
+struct Group {
+    frames: Vec<Vec<u8>>,
+    threads: Vec<(u32, u64, Vec<u8>)>,
+}
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+        }
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut index: HashMap<Vec<Vec<u8>>, usize> = HashMap::new();
+
+        let threads = process.as_mut().GetNumThreads() as usize;
+        for thread_idx in 0..threads {
+            moveit!(let mut thread = process.as_mut().GetThreadAtIndex(thread_idx));
+
+            let thread_name = cstr!(unsafe thread.GetName())
+                .map(|name| Vec::from(name.to_bytes()))
+                .unwrap_or_default();
+            let frames = backtrace(thread.as_mut());
+
+            let idx = *index.entry(frames.clone()).or_insert_with(|| {
+                groups.push(Group { frames, threads: Vec::new() });
+                groups.len() - 1
+            });
+
+            groups[idx].threads.push((thread.GetIndexID(), thread.GetThreadID(), thread_name));
+        }
+
+        groups.sort_by_key(|group| std::cmp::Reverse(group.threads.len()));
+
+        for group in groups.iter().filter(|group| group.threads.len() >= self.min) {
+            write!(stdout, "{} threads:", group.threads.len())?;
+            for (i, (index, tid, name)) in group.threads.iter().enumerate() {
+                let sep = if i == 0 { "" } else { "," };
+                write!(stdout, "{} #{} tid= {} {:?}", sep, index, tid, name.as_bstr())?;
+            }
+            writeln!(stdout)?;
+
+            for (i, frame) in group.frames.iter().enumerate() {
+                writeln!(stdout, "  #{} {}", i, frame.as_bstr())?;
+            }
+
+            writeln!(stdout)?;
+        }
+
+        writeln!(stdout, "{} threads, {} unique stacks", threads, groups.len())?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Symbolized frames as `module`function`, inlined frames included,
+/// unknown frames fall back to pc.
+pub fn backtrace(mut thread: Pin<&mut lldb::SBThread>) -> Vec<Vec<u8>> {
+    let mut list = Vec::new();
+
+    let frames = thread.as_mut().GetNumFrames();
+    for frame_idx in 0..frames {
+        moveit!{
+            let mut frame = thread.as_mut().GetFrameAtIndex(frame_idx);
+            let mut module = frame.as_mut().GetModule();
+            let filespec = module.as_ref().GetFileSpec();
+        }
+
+        let module_name = cstr!(unsafe filespec.GetFilename());
+        let function_name = cstr!(unsafe frame.as_mut().GetFunctionName());
+
+        let mut name = Vec::new();
+        if let Some(module_name) = module_name {
+            name.extend_from_slice(module_name.to_bytes());
+            name.push(b'`');
+        }
+        match function_name {
+            Some(function_name) => name.extend_from_slice(function_name.to_bytes()),
+            None => name.extend_from_slice(format!("{:#x}", frame.GetPC()).as_bytes())
+        }
+
+        list.push(name);
+    }
+
+    list
+}
@@ -0,0 +1,340 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::str::FromStr;
+use argh::FromArgs;
+use autocxx::moveit::moveit;
+use bstr::ByteSlice;
+use crate::sys::lldb;
+use crate::util::{ print_pretty_bytes, memory_regions, read_memory, u64ptr };
+
+
+/// MyDbg String command
+#[derive(FromArgs)]
+pub struct Command {
+    /// string address, or address of string header
+    #[argh(positional)]
+    address: String,
+
+    /// string layout, c/utf16/libstdcxx/libcxx/rust-string/rust-str/vec, default c
+    #[argh(option, short = 'm', default = "Mode::C")]
+    mode: Mode,
+
+    /// max read length, default 4096
+    #[argh(option, default = "4096")]
+    max: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    C,
+    Utf16,
+    Libstdcxx,
+    Libcxx,
+    RustString,
+    RustStr,
+    Vec,
+}
+
+/// Where string bytes live.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub ptr: u64,
+    pub len: u64,
+    pub cap: Option<u64>,
+    pub inline: bool,
+}
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let addr = u64ptr(self.address.as_str())?;
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let mut error = lldb::SBError::new();
+        }
+
+        let mut buf: Vec<u8> = Vec::new();
+
+        let layout = match self.mode {
+            Mode::C | Mode::Utf16 => {
+                let unit = if self.mode == Mode::Utf16 { 2 } else { 1 };
+                let bytes = read_until_nul(process.as_mut(), addr, unit, self.max, error.as_mut())?;
+                let bytes = bytes.as_slice();
+
+                if self.mode == Mode::Utf16 {
+                    let units = bytes.chunks_exact(2)
+                        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                        .collect::<Vec<_>>();
+                    let s = char::decode_utf16(units.iter().copied())
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect::<String>();
+
+                    writeln!(stdout, "mode= utf16 ptr= {:018p} len= {}", addr as *const u8, units.len())?;
+                    writeln!(stdout, "{:?}", s)?;
+
+                    if let Some(pos) = char::decode_utf16(units.iter().copied()).position(|c| c.is_err()) {
+                        writeln!(stdout, "invalid utf-16 at unit {}", pos)?;
+                    }
+                } else {
+                    writeln!(stdout, "mode= c ptr= {:018p} len= {}", addr as *const u8, bytes.len())?;
+                    print_str(&mut stdout, bytes)?;
+                }
+
+                if bytes.len() >= self.max {
+                    writeln!(stdout, "no NUL within {} bytes", self.max)?;
+                }
+
+                stdout.flush()?;
+
+                return Ok(());
+            },
+            Mode::Libstdcxx => {
+                let header = read_memory(process.as_mut(), &mut buf, addr, 32, error.as_mut())?;
+                libstdcxx_layout(header, addr)
+            },
+            Mode::Libcxx => {
+                let header = read_memory(process.as_mut(), &mut buf, addr, 24, error.as_mut())?;
+                libcxx_layout(header, addr)
+            },
+            Mode::RustStr => {
+                let header = read_memory(process.as_mut(), &mut buf, addr, 16, error.as_mut())?;
+                let words = words::<2>(header);
+                Layout { ptr: words[0], len: words[1], cap: None, inline: false }
+            },
+            Mode::RustString | Mode::Vec => {
+                let regions = memory_regions(process.as_mut());
+                let header = read_memory(process.as_mut(), &mut buf, addr, 24, error.as_mut())?;
+
+                rust_vec_layout(
+                    words::<3>(header),
+                    |ptr| regions.iter().any(|region| region.range.contains(&ptr))
+                ).ok_or_else(|| anyhow::format_err!("does not look like a Vec: {:x?}", words::<3>(header)))?
+            }
+        };
+
+        write!(
+            stdout,
+            "mode= {} ptr= {:018p} len= {}",
+            self.mode,
+            layout.ptr as *const u8,
+            layout.len
+        )?;
+        if let Some(cap) = layout.cap {
+            write!(stdout, " cap= {}", cap)?;
+        }
+        if layout.inline {
+            write!(stdout, " (sso)")?;
+        }
+        writeln!(stdout)?;
+
+        let len = usize::try_from(layout.len)?;
+        let len = if len > self.max {
+            writeln!(stdout, "truncated to {} bytes", self.max)?;
+            self.max
+        } else {
+            len
+        };
+
+        let bytes = if len == 0 {
+            &[][..]
+        } else {
+            read_memory(process.as_mut(), &mut buf, layout.ptr, len, error.as_mut())?
+        };
+
+        if self.mode == Mode::Vec {
+            print_pretty_bytes(&mut stdout, layout.ptr, bytes)?;
+        } else {
+            print_str(&mut stdout, bytes)?;
+        }
+
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+fn print_str(stdout: &mut dyn Write, bytes: &[u8]) -> anyhow::Result<()> {
+    writeln!(stdout, "{:?}", bytes.as_bstr())?;
+
+    if let Err(err) = std::str::from_utf8(bytes) {
+        writeln!(stdout, "invalid utf-8 at offset {}", err.valid_up_to())?;
+    }
+
+    Ok(())
+}
+
+/// Read `unit` sized chars until NUL, without crossing into unmapped pages.
+fn read_until_nul(
+    mut process: Pin<&mut lldb::SBProcess>,
+    addr: u64,
+    unit: usize,
+    max: usize,
+    mut error: Pin<&mut lldb::SBError>
+) -> anyhow::Result<Vec<u8>> {
+    const PAGE_SIZE: u64 = 4096;
+
+    let mut output = Vec::new();
+    let mut buf = Vec::new();
+
+    while output.len() < max {
+        let chunk_addr = addr + output.len() as u64;
+        let page_end = (chunk_addr / PAGE_SIZE + 1) * PAGE_SIZE;
+        let size = std::cmp::min((page_end - chunk_addr) as usize, max - output.len());
+        let size = std::cmp::max(size - size % unit, unit);
+
+        let chunk = read_memory(process.as_mut(), &mut buf, chunk_addr, size, error.as_mut())?;
+
+        match chunk.chunks_exact(unit).position(|c| c.iter().all(|&b| b == 0)) {
+            Some(pos) => {
+                output.extend_from_slice(&chunk[..pos * unit]);
+                break
+            },
+            None => output.extend_from_slice(chunk)
+        }
+    }
+
+    Ok(output)
+}
+
+pub fn words<const N: usize>(bytes: &[u8]) -> [u64; N] {
+    let mut words = [0; N];
+    for (word, b) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(b.try_into().unwrap());
+    }
+    words
+}
+
+/// libstdc++ `std::string`: `{ ptr, len, union { buf[16], cap } }`
+pub fn libstdcxx_layout(header: &[u8], addr: u64) -> Layout {
+    let [ptr, len, cap, _] = words::<4>(header);
+    let inline = ptr == addr + 16;
+
+    Layout {
+        ptr,
+        len,
+        cap: if inline { None } else { Some(cap) },
+        inline
+    }
+}
+
+/// libc++ `std::string`, little endian.
+///
+/// The lowest bit of first byte is the long flag,
+/// long layout is `{ cap, len, ptr }`, short layout is `{ len << 1, buf[23] }`.
+pub fn libcxx_layout(header: &[u8], addr: u64) -> Layout {
+    let [cap, len, ptr] = words::<3>(header);
+
+    if header[0] & 1 == 0 {
+        Layout {
+            ptr: addr + 1,
+            len: (header[0] >> 1) as u64,
+            cap: None,
+            inline: true
+        }
+    } else {
+        Layout {
+            ptr,
+            len,
+            cap: Some(cap & !1),
+            inline: false
+        }
+    }
+}
+
+/// Rust `Vec<T>`/`String` header, field order is not fixed,
+/// so the pointer is the word that points into mapped memory and `len <= cap`.
+pub fn rust_vec_layout(words: [u64; 3], is_mapped: impl Fn(u64) -> bool) -> Option<Layout> {
+    // empty `String::new()` or `Vec::new()` hold a dangling pointer, the alignment
+    let is_dangling = |ptr: u64| ptr.is_power_of_two() && ptr <= 4096;
+    if let [ptr] = words.iter().filter(|&&word| word != 0).collect::<Vec<_>>()[..] {
+        if is_dangling(*ptr) && !is_mapped(*ptr) {
+            return Some(Layout { ptr: *ptr, len: 0, cap: Some(0), inline: false });
+        }
+    }
+
+    let ptr_idx = (0..3).find(|&i| words[i] != 0 && is_mapped(words[i]))?;
+    let mut rest = (0..3).filter(|&i| i != ptr_idx).map(|i| words[i]);
+    let (a, b) = (rest.next()?, rest.next()?);
+
+    Some(Layout {
+        ptr: words[ptr_idx],
+        len: std::cmp::min(a, b),
+        cap: Some(std::cmp::max(a, b)),
+        inline: false
+    })
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "c" => Mode::C,
+            "utf16" => Mode::Utf16,
+            "libstdcxx" => Mode::Libstdcxx,
+            "libcxx" => Mode::Libcxx,
+            "rust-string" => Mode::RustString,
+            "rust-str" => Mode::RustStr,
+            "vec" => Mode::Vec,
+            _ => return Err(format!("unknown mode: {}", s))
+        })
+    }
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Mode::C => "c",
+            Mode::Utf16 => "utf16",
+            Mode::Libstdcxx => "libstdcxx",
+            Mode::Libcxx => "libcxx",
+            Mode::RustString => "rust-string",
+            Mode::RustStr => "rust-str",
+            Mode::Vec => "vec",
+        })
+    }
+}
+
+#[test]
+fn test_string_layout() {
+    let addr = 0x1000;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&0x1010u64.to_le_bytes());
+    header.extend_from_slice(&5u64.to_le_bytes());
+    header.extend_from_slice(b"hello\0\0\0\0\0\0\0\0\0\0\0");
+    assert_eq!(
+        libstdcxx_layout(&header, addr),
+        Layout { ptr: 0x1010, len: 5, cap: None, inline: true }
+    );
+
+    let mut header = vec![5 << 1];
+    header.extend_from_slice(b"hello");
+    header.resize(24, 0);
+    assert_eq!(
+        libcxx_layout(&header, addr),
+        Layout { ptr: 0x1001, len: 5, cap: None, inline: true }
+    );
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&(48u64 | 1).to_le_bytes());
+    header.extend_from_slice(&30u64.to_le_bytes());
+    header.extend_from_slice(&0x2000u64.to_le_bytes());
+    assert_eq!(
+        libcxx_layout(&header, addr),
+        Layout { ptr: 0x2000, len: 30, cap: Some(48), inline: false }
+    );
+
+    assert_eq!(
+        rust_vec_layout([32, 0x2000, 7], |ptr| (0x2000..0x3000).contains(&ptr)),
+        Some(Layout { ptr: 0x2000, len: 7, cap: Some(32), inline: false })
+    );
+    assert_eq!(rust_vec_layout([32, 0x9000, 7], |ptr| (0x2000..0x3000).contains(&ptr)), None);
+    assert_eq!(
+        rust_vec_layout([0, 1, 0], |ptr| (0x2000..0x3000).contains(&ptr)),
+        Some(Layout { ptr: 1, len: 0, cap: Some(0), inline: false })
+    );
+    assert_eq!(rust_vec_layout([0, 3, 0], |ptr| (0x2000..0x3000).contains(&ptr)), None);
+}
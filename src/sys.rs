@@ -8,6 +8,17 @@ autocxx::include_cpp! {
     generate!("lldb::SBThread")
     generate!("lldb::SBFrame")
     generate!("lldb::SBSymbol")
+    generate!("lldb::SBSymbolContextList")
+    generate!("lldb::SBSymbolContext")
+    generate!("lldb::SBAddress")
+    generate!("lldb::SBModule")
+    generate!("lldb::SBFileSpec")
+    generate!("lldb::SBLineEntry")
+    generate!("lldb::SBType")
+    generate!("lldb::SBTypeMember")
+    generate!("lldb::SBTypeList")
+    generate!("lldb::SBInstructionList")
+    generate!("lldb::SBInstruction")
     generate!("lldb::SBValueList")
     generate!("lldb::SBValue")
     generate!("lldb::SBData")
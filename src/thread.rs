@@ -1,18 +1,66 @@
-use std::mem;
 use std::io::{ self, Write };
 use std::pin::Pin;
+use std::ops::Range;
 use argh::FromArgs;
 use autocxx::moveit::moveit;
 use bstr::ByteSlice;
 use human_size::SpecificSize;
 use human_size::multiples::{ Byte, Kibibyte };
 use crate::sys::lldb;
+use crate::procfs::{ read_stack_limit, read_task };
+use crate::util::{ ByteSize, Region, frame_location, glob_match, memory_regions, read_memory, u64ptr };
 
 
 /// MyDbg thread command
 #[derive(FromArgs)]
 pub struct Command {
-    //
+    /// report all threads
+    #[argh(switch)]
+    all: bool,
+
+    /// report thread by thread id
+    #[argh(option)]
+    tid: Option<String>,
+
+    /// report thread by index, same as `thread list`
+    #[argh(option)]
+    index: Option<u32>,
+
+    /// report threads whose name matches glob
+    #[argh(option)]
+    name: Option<String>,
+
+    /// warn when stack usage is over this percent, default 80
+    #[argh(option, default = "80.0")]
+    warn: f64,
+
+    /// fail when any frame is larger than this, e.g. 32KiB
+    #[argh(option)]
+    max_frame: Option<ByteSize>,
+
+    /// fail when any thread uses more stack than this, e.g. 512KiB
+    #[argh(option)]
+    max_stack: Option<ByteSize>,
+
+    /// rank the N largest locals across threads, all threads by default
+    #[argh(option)]
+    top_locals: Option<usize>,
+
+    /// print local value summary and storage location
+    #[argh(switch)]
+    values: bool,
+
+    /// print kernel thread state from /proc, local linux process only
+    #[argh(switch)]
+    os: bool,
+}
+
+struct RankedLocal {
+    thread_index: u32,
+    thread_name: Vec<u8>,
+    frame_index: u32,
+    symbol: Vec<u8>,
+    local: Local,
 }
 
 impl Command {
@@ -22,87 +70,606 @@ impl Command {
         moveit!{
             let mut target = debugger.GetSelectedTarget();
             let mut process = target.as_mut().GetProcess();
-            let mut thread = process.as_mut().GetSelectedThread();
         }
 
-        let mut sp_range = None;
-        let mut last_sp = None;
-
-        let frames = thread.as_mut().GetNumFrames();
-        for frame_idx in 0..frames {
-            moveit!{
-                let mut frame = thread.as_mut().GetFrameAtIndex(frame_idx);
-                let mut symbol = frame.as_mut().GetSymbol();
-            }
+        // the ranking skips the per thread report, budgets would pass silently
+        anyhow::ensure!(
+            self.top_locals.is_none() || (self.max_frame.is_none() && self.max_stack.is_none()),
+            "--top-locals can not be used with --max-frame or --max-stack"
+        );
 
-            // find stack scope
-            // https://github.com/llvm/llvm-project/blob/main/lldb/examples/darwin/heap_find/heap.py#L1172
-            let current_sp = frame.GetSP();
-            let sp_range = sp_range.get_or_insert_with(|| current_sp..current_sp);
-            sp_range.start = std::cmp::min(sp_range.start, current_sp);
-            sp_range.end = std::cmp::max(sp_range.end, current_sp);
+        let tid = self.tid.as_deref().map(u64ptr).transpose()?;
+        let index = if !self.all
+            && tid.is_none()
+            && self.index.is_none()
+            && self.name.is_none()
+            && self.top_locals.is_none()
+        {
+            moveit!(let thread = process.as_mut().GetSelectedThread());
+            Some(thread.GetIndexID())
+        } else {
+            self.index
+        };
 
-            let last_sp = mem::replace(last_sp.get_or_insert(current_sp), current_sp);
-            let stack_size = current_sp.saturating_sub(last_sp);
-
-            if frame.IsInlined1() && stack_size == 0 {
-                continue;
-            }
+        let pid = process.as_mut().GetProcessID();
+        let regions = memory_regions(process.as_mut());
+        let stack_limit = read_stack_limit(pid);
+        let mut count = 0;
+        let mut over_budget = Vec::new();
+        let mut ranking = Vec::new();
 
-            let stack_size = SpecificSize::new(stack_size as f64, Byte)?.into::<Kibibyte>();
+        let threads = process.as_mut().GetNumThreads() as usize;
+        for thread_idx in 0..threads {
+            moveit!(let mut thread = process.as_mut().GetThreadAtIndex(thread_idx));
 
-            let symbol_name = cstr!(unsafe symbol.GetName())
+            let thread_name = cstr!(unsafe thread.GetName())
                 .map(|name| Vec::from(name.to_bytes()))
                 .unwrap_or_default();
 
-            moveit!{
-                let variables = frame.as_mut().GetVariables(
-                    true,
-                    true,
-                    false,
-                    true
-                );
+            if tid.is_some_and(|tid| tid != thread.GetThreadID())
+                || index.is_some_and(|index| index != thread.GetIndexID())
+                || self.name.as_ref().is_some_and(|name| !glob_match(name.as_bytes(), &thread_name))
+            {
+                continue
             }
-            let mut list = Vec::new();
 
-            let count = variables.GetSize();
-            for i in 0..count {
-                moveit!{
-                    let mut value = variables.GetValueAtIndex(i);
+            count += 1;
+
+            if self.top_locals.is_some() {
+                let frames = thread.as_mut().GetNumFrames();
+                for frame_idx in 0..frames {
+                    moveit!{
+                        let mut frame = thread.as_mut().GetFrameAtIndex(frame_idx);
+                        let mut symbol = frame.as_mut().GetSymbol();
+                    }
+
+                    // inlined frames have their own locals but share the concrete symbol
+                    let symbol_name = if frame.IsInlined1() {
+                        cstr!(unsafe frame.as_mut().GetFunctionName())
+                    } else {
+                        cstr!(unsafe symbol.GetName())
+                    };
+                    let symbol_name = symbol_name
+                        .map(|name| Vec::from(name.to_bytes()))
+                        .unwrap_or_default();
+
+                    for local in frame_locals(frame.as_mut(), false) {
+                        ranking.push(RankedLocal {
+                            thread_index: thread.GetIndexID(),
+                            thread_name: thread_name.clone(),
+                            frame_index: frame_idx,
+                            symbol: symbol_name.clone(),
+                            local
+                        });
+                    }
                 }
 
-                let ty = cstr!(unsafe value.as_mut().GetTypeName())
-                    .map(|name| Vec::from(name.to_bytes()))
-                    .unwrap_or_default();
-                let name = cstr!(unsafe value.as_mut().GetName())
-                    .map(|name| Vec::from(name.to_bytes()))
-                    .unwrap_or_default();
-                let size = value.as_mut().GetByteSize();
-                let size = SpecificSize::new(size as f64, Byte)?.into::<Kibibyte>();
-                list.push((ty, name, size));
+                continue
             }
 
             writeln!(
                 &mut stdout,
-                "#{} size= {}; frame= {:?}",
-                frame_idx,
-                stack_size,
-                symbol_name.as_bstr()
+                "thread #{} tid= {} name= {:?}",
+                thread.GetIndexID(),
+                thread.GetThreadID(),
+                thread_name.as_bstr()
             )?;
 
-            for (ty, name, size) in list {
+            if self.os {
+                match read_task(pid, thread.GetThreadID()) {
+                    Ok(task) => writeln!(&mut stdout, "os: {}", task)?,
+                    Err(err) => writeln!(&mut stdout, "os: not available, {}", err)?
+                }
+            }
+
+            let headroom = print_stack_headroom(&mut stdout, thread.as_mut(), &regions, stack_limit, self.warn)?;
+            writeln!(&mut stdout)?;
+
+            let frames = print_thread_report(&mut stdout, thread.as_mut(), self.values)?;
+
+            if let Some(ByteSize(max)) = self.max_frame {
+                for frame in frames.iter().filter(|frame| frame.size > max) {
+                    over_budget.push(format!(
+                        "thread #{} frame #{} {:?} size= {} > {}",
+                        thread.GetIndexID(),
+                        frame.index,
+                        frame.symbol.as_bstr(),
+                        kib(frame.size)?,
+                        kib(max)?
+                    ));
+                }
+            }
+
+            if let Some(ByteSize(max)) = self.max_stack {
+                let used = match headroom {
+                    Some(headroom) => headroom.used,
+                    None => frames.iter().map(|frame| frame.size).sum()
+                };
+
+                if used > max {
+                    over_budget.push(format!(
+                        "thread #{} {:?} stack used= {} > {}",
+                        thread.GetIndexID(),
+                        thread_name.as_bstr(),
+                        kib(used)?,
+                        kib(max)?
+                    ));
+                }
+            }
+        }
+
+        anyhow::ensure!(count != 0, "no thread matched");
+
+        if let Some(n) = self.top_locals {
+            ranking.sort_by_key(|item| std::cmp::Reverse(item.local.size));
+
+            for (i, item) in ranking.iter().take(n).enumerate() {
                 writeln!(
                     &mut stdout,
-                    "let {}: {:?} = {};",
-                    name.as_bstr(),
-                    ty.as_bstr(),
-                    size
+                    "{}. {} thread #{} {:?} frame #{} {:?}: let {}: {:?};",
+                    i + 1,
+                    kib(item.local.size)?,
+                    item.thread_index,
+                    item.thread_name.as_bstr(),
+                    item.frame_index,
+                    item.symbol.as_bstr(),
+                    item.local.name.as_bstr(),
+                    item.local.ty.as_bstr()
                 )?;
             }
+        }
 
-            writeln!(&mut stdout)?;
+        for line in over_budget.iter() {
+            writeln!(&mut stdout, "over budget: {}", line)?;
         }
 
+        stdout.flush()?;
+
+        anyhow::ensure!(over_budget.is_empty(), "{} over budget", over_budget.len());
+
         Ok(())
     }
 }
+
+pub struct Headroom {
+    pub range: Range<u64>,
+    pub used: u64,
+    pub free: u64,
+    pub guard: u64,
+}
+
+impl Headroom {
+    pub fn percent(&self) -> f64 {
+        self.used as f64 * 100.0 / (self.range.end - self.range.start) as f64
+    }
+}
+
+/// Find the stack mapping that contains `sp`, stack grows down.
+///
+/// An adjacent inaccessible mapping below it is counted as guard page.
+/// The main thread `[stack]` grows on demand, so its size is `stack_limit` if known,
+/// and it has no guard mapping.
+pub fn stack_headroom(regions: &[Region], sp: u64, stack_limit: Option<u64>) -> Option<Headroom> {
+    let idx = regions.iter().position(|region| region.range.contains(&sp))?;
+    let mut range = regions[idx].range.clone();
+
+    if regions[idx].name.as_deref() == Some(b"[stack]") {
+        if let Some(limit) = stack_limit {
+            range.start = std::cmp::min(range.start, range.end.saturating_sub(limit));
+        }
+
+        return Some(Headroom {
+            used: range.end - sp,
+            free: sp - range.start,
+            range,
+            guard: 0,
+        });
+    }
+
+    let guard = idx.checked_sub(1)
+        .map(|idx| &regions[idx])
+        .filter(|region| region.range.end == range.start)
+        .filter(|region| !region.readable && !region.writable)
+        .map(|region| region.range.end - region.range.start)
+        .unwrap_or(0);
+
+    Some(Headroom {
+        used: range.end - sp,
+        free: sp - range.start,
+        range,
+        guard,
+    })
+}
+
+fn kib(size: u64) -> anyhow::Result<SpecificSize<Kibibyte>> {
+    Ok(SpecificSize::new(size as f64, Byte)?.into::<Kibibyte>())
+}
+
+pub fn print_stack_headroom(
+    stdout: &mut dyn Write,
+    mut thread: Pin<&mut lldb::SBThread>,
+    regions: &[Region],
+    stack_limit: Option<u64>,
+    warn: f64,
+) -> anyhow::Result<Option<Headroom>> {
+    moveit!(let frame = thread.as_mut().GetFrameAtIndex(0));
+
+    let sp = frame.GetSP();
+    let headroom = match stack_headroom(regions, sp, stack_limit) {
+        Some(headroom) => headroom,
+        None => {
+            writeln!(stdout, "stack= unknown, sp {:018p} is not mapped", sp as *const u8)?;
+            return Ok(None);
+        }
+    };
+
+    writeln!(
+        stdout,
+        "stack= [{:018p}-{:018p}] size= {} used= {} free= {} ({:.1}%) guard= {}",
+        headroom.range.start as *const u8,
+        headroom.range.end as *const u8,
+        kib(headroom.range.end - headroom.range.start)?,
+        kib(headroom.used)?,
+        kib(headroom.free)?,
+        headroom.percent(),
+        kib(headroom.guard)?
+    )?;
+
+    if headroom.percent() > warn {
+        writeln!(stdout, "warning: stack usage {:.1}% is over {}%", headroom.percent(), warn)?;
+    }
+
+    Ok(Some(headroom))
+}
+
+pub struct FrameInfo {
+    pub index: u32,
+    pub symbol: Vec<u8>,
+    pub size: u64,
+}
+
+pub struct Local {
+    pub ty: Vec<u8>,
+    pub name: Vec<u8>,
+    pub size: u64,
+    pub value: Option<Vec<u8>>,
+    pub location: Option<Vec<u8>>,
+    pub available: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Storage {
+    Register(Vec<u8>),
+    /// offset from CFA
+    Stack(i64),
+    Memory(u64),
+    OptimizedOut,
+}
+
+pub fn frame_locals(frame: Pin<&mut lldb::SBFrame>, with_values: bool) -> Vec<Local> {
+    moveit!{
+        let variables = frame.GetVariables(
+            true,
+            true,
+            false,
+            true
+        );
+    }
+    let mut list = Vec::new();
+
+    let count = variables.GetSize();
+    for i in 0..count {
+        moveit!{
+            let mut value = variables.GetValueAtIndex(i);
+        }
+
+        let ty = cstr!(unsafe value.as_mut().GetTypeName())
+            .map(|name| Vec::from(name.to_bytes()))
+            .unwrap_or_default();
+        let name = cstr!(unsafe value.as_mut().GetName())
+            .map(|name| Vec::from(name.to_bytes()))
+            .unwrap_or_default();
+        let size = value.as_mut().GetByteSize();
+
+        // location comes from debug info, it does not read target memory
+        let location = cstr!(unsafe value.as_mut().GetLocation())
+            .map(|location| Vec::from(location.to_bytes()));
+
+        let (summary, available) = if with_values {
+            moveit!(let error = value.as_mut().GetError());
+
+            let summary = cstr!(unsafe value.as_mut().GetSummary())
+                .or_else(|| cstr!(unsafe value.as_mut().GetValue()))
+                .map(|value| Vec::from(value.to_bytes()));
+            (summary, error.Success())
+        } else {
+            (None, true)
+        };
+
+        list.push(Local { ty, name, size, value: summary, location, available });
+    }
+
+    list
+}
+
+/// Classify lldb value location, memory locations within `sp..cfa`
+/// or right above CFA (stack arguments) are stack slots.
+pub fn local_storage(location: Option<&[u8]>, available: bool, sp: u64, cfa: Option<u64>) -> Storage {
+    let location = match location {
+        Some(location) if available && !location.is_empty() => location,
+        _ => return Storage::OptimizedOut
+    };
+
+    let addr = location.to_str().ok().and_then(|location| u64ptr(location).ok());
+
+    match (addr, cfa) {
+        (Some(addr), Some(cfa)) if addr >= sp && addr < cfa.saturating_add(0x100) =>
+            Storage::Stack(addr.wrapping_sub(cfa) as i64),
+        (Some(addr), _) => Storage::Memory(addr),
+        (None, _) => Storage::Register(location.to_vec())
+    }
+}
+
+impl std::fmt::Display for Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Storage::Register(name) => write!(f, "register {}", name.as_bstr()),
+            Storage::Stack(offset) if *offset < 0 => write!(f, "stack CFA-{:#x}", offset.unsigned_abs()),
+            Storage::Stack(offset) => write!(f, "stack CFA+{:#x}", offset),
+            Storage::Memory(addr) => write!(f, "memory {:018p}", *addr as *const u8),
+            Storage::OptimizedOut => write!(f, "optimized out")
+        }
+    }
+}
+
+/// Where the return address of a frame is saved.
+///
+/// x86 pushes it right below the CFA, arm saves `{ fp, lr }` at the frame pointer.
+pub fn return_address_slot(triple: &[u8], ptr_size: u64, cfa: u64, fp: u64) -> Option<u64> {
+    if triple.starts_with(b"x86_64") || triple.starts_with(b"i386") || triple.starts_with(b"i686") {
+        cfa.checked_sub(ptr_size)
+    } else if triple.starts_with(b"aarch64") || triple.starts_with(b"arm") {
+        if fp != 0 && fp < cfa {
+            fp.checked_add(ptr_size)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+/// Where the caller frame pointer is saved, if the frame keeps one.
+///
+/// x86 pushes it right below the return address, arm saves it at the frame pointer.
+pub fn frame_pointer_slot(triple: &[u8], ptr_size: u64, cfa: u64, fp: u64) -> Option<u64> {
+    if triple.starts_with(b"x86_64") || triple.starts_with(b"i386") || triple.starts_with(b"i686") {
+        cfa.checked_sub(2 * ptr_size)
+    } else if triple.starts_with(b"aarch64") || triple.starts_with(b"arm") {
+        (fp != 0 && fp < cfa).then_some(fp)
+    } else {
+        None
+    }
+}
+
+struct InlinedFrame {
+    index: u32,
+    function: Vec<u8>,
+    location: Option<Vec<u8>>,
+    locals: Vec<Local>,
+}
+
+/// Frame size is `CFA - SP`, the CFA is the caller SP before the call,
+/// so it covers the innermost frame and the return address slot.
+///
+/// Inlined frames share SP and CFA with their concrete frame, they are printed
+/// nested under it and their stack usage is attributed to it.
+pub fn print_thread_report(
+    stdout: &mut dyn Write,
+    mut thread: Pin<&mut lldb::SBThread>,
+    with_values: bool,
+) -> anyhow::Result<Vec<FrameInfo>> {
+    moveit!{
+        let mut process = thread.as_mut().GetProcess();
+        let mut target = process.as_mut().GetTarget();
+        let mut error = lldb::SBError::new();
+    }
+
+    let triple = cstr!(unsafe target.as_mut().GetTriple())
+        .map(|triple| Vec::from(triple.to_bytes()))
+        .unwrap_or_default();
+    let ptr_size = target.as_mut().GetAddressByteSize() as u64;
+
+    let mut buf = Vec::new();
+    let mut frame_list = Vec::new();
+    let mut inlined = Vec::new();
+
+    let frames = thread.as_mut().GetNumFrames();
+    for frame_idx in 0..frames {
+        moveit!{
+            let mut frame = thread.as_mut().GetFrameAtIndex(frame_idx);
+            let mut symbol = frame.as_mut().GetSymbol();
+        }
+
+        // inlined frames come before the concrete frame they are inlined into
+        if frame.IsInlined1() {
+            inlined.push(InlinedFrame {
+                index: frame_idx,
+                function: cstr!(unsafe frame.as_mut().GetFunctionName())
+                    .map(|name| Vec::from(name.to_bytes()))
+                    .unwrap_or_default(),
+                location: frame_location(frame.as_mut()),
+                locals: frame_locals(frame.as_mut(), with_values)
+            });
+            continue;
+        }
+
+        let sp = frame.GetSP();
+        let fp = frame.GetFP();
+        let cfa = frame.GetCFA();
+        let cfa = (cfa != u64::MAX && cfa >= sp).then_some(cfa);
+        let stack_size = cfa.map(|cfa| cfa - sp).unwrap_or(0);
+
+        let symbol_name = cstr!(unsafe symbol.GetName())
+            .map(|name| Vec::from(name.to_bytes()))
+            .unwrap_or_default();
+
+        let list = frame_locals(frame.as_mut(), with_values);
+
+        write!(
+            stdout,
+            "#{} size= {}; sp= {:018p} fp= {:018p}",
+            frame_idx,
+            kib(stack_size)?,
+            sp as *const u8,
+            fp as *const u8
+        )?;
+
+        match cfa {
+            Some(cfa) => write!(stdout, " cfa= {:018p}", cfa as *const u8)?,
+            None => write!(stdout, " cfa= unknown")?
+        }
+
+        let slots = [
+            ("ra", cfa.and_then(|cfa| return_address_slot(&triple, ptr_size, cfa, fp))),
+            ("saved_fp", cfa.and_then(|cfa| frame_pointer_slot(&triple, ptr_size, cfa, fp))),
+        ];
+
+        for (name, slot) in slots.into_iter().filter_map(|(name, slot)| Some((name, slot?))) {
+            write!(stdout, " {}= [{:018p}]", name, slot as *const u8)?;
+
+            if let Ok(bytes) = read_memory(process.as_mut(), &mut buf, slot, ptr_size as usize, error.as_mut()) {
+                let mut value = [0; 8];
+                value[..bytes.len()].copy_from_slice(bytes);
+                write!(stdout, " {:018p}", u64::from_le_bytes(value) as *const u8)?;
+            }
+        }
+
+        write!(stdout, "; frame= {:?}", symbol_name.as_bstr())?;
+        if let Some(location) = frame_location(frame.as_mut()) {
+            write!(stdout, " at {}", location.as_bstr())?;
+        }
+        writeln!(stdout)?;
+
+        for local in list.iter() {
+            print_local(stdout, "", local, with_values, sp, cfa)?;
+        }
+
+        // innermost inlined frame first, same order as `bt`
+        for item in inlined.drain(..) {
+            write!(stdout, "  inlined #{} {:?}", item.index, item.function.as_bstr())?;
+            if let Some(location) = item.location.as_ref() {
+                write!(stdout, " at {}", location.as_bstr())?;
+            }
+            writeln!(stdout)?;
+
+            for local in item.locals.iter() {
+                print_local(stdout, "  ", local, with_values, sp, cfa)?;
+            }
+        }
+
+        writeln!(stdout)?;
+
+        frame_list.push(FrameInfo {
+            index: frame_idx,
+            symbol: symbol_name,
+            size: stack_size
+        });
+    }
+
+    Ok(frame_list)
+}
+
+fn print_local(
+    stdout: &mut dyn Write,
+    indent: &str,
+    local: &Local,
+    with_values: bool,
+    sp: u64,
+    cfa: Option<u64>,
+) -> anyhow::Result<()> {
+    write!(
+        stdout,
+        "{}let {}: {:?} = {};",
+        indent,
+        local.name.as_bstr(),
+        local.ty.as_bstr(),
+        kib(local.size)?
+    )?;
+
+    if with_values {
+        let storage = local_storage(local.location.as_deref(), local.available, sp, cfa);
+        write!(stdout, " // {}", storage)?;
+
+        if let Some(value) = local.value.as_ref() {
+            let value = value.as_bstr();
+            match value.char_indices().nth(64) {
+                Some((pos, _, _)) => write!(stdout, ", {}...", value[..pos].as_bstr())?,
+                None => write!(stdout, ", {}", value)?
+            }
+        }
+    }
+
+    writeln!(stdout)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_local_storage() {
+    let cfa = Some(0x2000);
+
+    assert_eq!(local_storage(Some(b"0x1ff0"), true, 0x1f00, cfa), Storage::Stack(-0x10));
+    assert_eq!(local_storage(Some(b"0x2010"), true, 0x1f00, cfa), Storage::Stack(0x10));
+    assert_eq!(local_storage(Some(b"0x7000"), true, 0x1f00, cfa), Storage::Memory(0x7000));
+    assert_eq!(local_storage(Some(b"rbx"), true, 0x1f00, cfa), Storage::Register(b"rbx".to_vec()));
+    assert_eq!(local_storage(Some(b""), true, 0x1f00, cfa), Storage::OptimizedOut);
+    assert_eq!(local_storage(Some(b"0x1ff0"), false, 0x1f00, cfa), Storage::OptimizedOut);
+}
+
+#[test]
+fn test_return_address_slot() {
+    assert_eq!(return_address_slot(b"x86_64-unknown-linux-gnu", 8, 0x1000, 0), Some(0xff8));
+    assert_eq!(return_address_slot(b"aarch64-unknown-linux-gnu", 8, 0x1000, 0xf00), Some(0xf08));
+    assert_eq!(return_address_slot(b"aarch64-unknown-linux-gnu", 8, 0x1000, 0), None);
+    assert_eq!(return_address_slot(b"riscv64-unknown-linux-gnu", 8, 0x1000, 0xf00), None);
+
+    assert_eq!(frame_pointer_slot(b"x86_64-unknown-linux-gnu", 8, 0x1000, 0), Some(0xff0));
+    assert_eq!(frame_pointer_slot(b"aarch64-unknown-linux-gnu", 8, 0x1000, 0xf00), Some(0xf00));
+    assert_eq!(frame_pointer_slot(b"aarch64-unknown-linux-gnu", 8, 0x1000, 0), None);
+    assert_eq!(frame_pointer_slot(b"riscv64-unknown-linux-gnu", 8, 0x1000, 0xf00), None);
+}
+
+#[test]
+fn test_stack_headroom() {
+    let region = |range: Range<u64>, readable| Region {
+        range,
+        readable,
+        writable: readable,
+        executable: false,
+        name: None
+    };
+    let regions = [
+        region(0x1000..0x2000, false),
+        region(0x2000..0x6000, true),
+    ];
+
+    let headroom = stack_headroom(&regions, 0x5000, None).unwrap();
+    assert_eq!(headroom.used, 0x1000);
+    assert_eq!(headroom.free, 0x3000);
+    assert_eq!(headroom.guard, 0x1000);
+    assert_eq!(headroom.percent(), 25.0);
+
+    assert!(stack_headroom(&regions, 0x8000, None).is_none());
+
+    let regions = [
+        region(0x1000..0x2000, false),
+        Region { name: Some(b"[stack]".to_vec()), ..region(0x2000..0x6000, true) },
+    ];
+
+    let headroom = stack_headroom(&regions, 0x5000, Some(0x10000)).unwrap();
+    assert_eq!(headroom.range, 0..0x6000);
+    assert_eq!(headroom.used, 0x1000);
+    assert_eq!(headroom.free, 0x5000);
+    assert_eq!(headroom.guard, 0);
+}
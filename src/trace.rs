@@ -1,9 +1,15 @@
+use std::io::{ self, Write };
 use std::pin::Pin;
-use std::sync::{ Mutex, LazyLock };
+use std::path::PathBuf;
+use std::ffi::CString;
+use std::sync::{ Mutex, MutexGuard, LazyLock };
+use std::time::{ Duration, Instant };
 use argh::FromArgs;
+use anyhow::Context;
 use autocxx::moveit::moveit;
+use bstr::ByteSlice;
 use crate::sys::lldb;
-use crate::util::u64ptr;
+use crate::util::{ HumanDuration, u64ptr, wait_for_stop };
 
 /// MyDbg Trace command
 #[derive(FromArgs)]
@@ -12,43 +18,170 @@ pub struct Command {
     #[argh(switch)]
     start: bool,
 
-    /// record regsiter
+    /// record registers or memory expressions when passing a point,
+    /// `<addr|symbol>:<reg|expr,...>`, e.g. `main:rdi,*(long*)$rsi`,
+    /// expressions can not contain `,`
     #[argh(option, short = 'r')]
     record: Option<String>,
 
-    /// end address
+    /// end address or symbol
     #[argh(option, short = 'e')]
     done: Option<String>,
+
+    /// print recorded registers
+    #[argh(switch)]
+    dump: bool,
+
+    /// export recorded registers as csv with `--dump`
+    #[argh(option, short = 'o')]
+    out: Option<PathBuf>,
+
+    /// stop tracing after N steps, default 100000
+    #[argh(option, default = "100000")]
+    max_steps: u64,
+
+    /// stop tracing after this long, default 60s
+    #[argh(option, default = "HumanDuration(Duration::from_secs(60))")]
+    timeout: HumanDuration,
+
+    /// use auto-continue breakpoints at record points instead of single-stepping
+    #[argh(switch)]
+    fast: bool,
+
+    #[argh(subcommand)]
+    sub: Option<Sub>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Sub {
+    List(List),
+    Remove(Remove),
+    Clear(Clear),
+    Save(Save),
+    Load(Load),
+}
+
+/// list trace points
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list")]
+struct List {}
+
+/// remove trace point by id
+#[derive(FromArgs)]
+#[argh(subcommand, name = "remove")]
+struct Remove {
+    #[argh(positional)]
+    id: usize,
+}
+
+/// remove all trace points and logs
+#[derive(FromArgs)]
+#[argh(subcommand, name = "clear")]
+struct Clear {}
+
+/// save trace points to file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "save")]
+struct Save {
+    #[argh(positional)]
+    file: PathBuf,
+}
+
+/// load trace points from file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "load")]
+struct Load {
+    #[argh(positional)]
+    file: PathBuf,
 }
 
 #[derive(Default)]
 struct Status {
-    records: Vec<u64>,
-    done: Vec<u64>,
+    next_id: usize,
+    records: Vec<Record>,
+    done: Vec<Point>,
+
+    logs: Vec<Log>,
+}
 
-    logs: Vec<(u32, u64)>,
+/// A trace location, `addr` is a file address of `module` so it survives ASLR,
+/// or an absolute address if no module contains it.
+struct Point {
+    id: usize,
+    spec: String,
+    module: Option<String>,
+    addr: u64,
+    /// load address in the current process
+    load: u64,
+}
+
+struct Record {
+    point: Point,
+    regs: Vec<String>,
+    hits: u64,
+}
+
+/// Line of a saved trace file, `<kind>\t<module|->\t<addr>\t<reg,...|->\t<spec>`.
+#[derive(PartialEq, Eq, Debug)]
+struct Saved {
+    record: bool,
+    module: Option<String>,
+    addr: u64,
+    regs: Vec<String>,
+    spec: String,
+}
+
+struct Log {
+    thread: u32,
+    pc: u64,
+    hit: Option<Hit>,
+}
+
+/// Register or expression values of the `count`th hit of record `record`.
+struct Hit {
+    record: usize,
+    count: u64,
+    values: Vec<Option<u64>>,
+}
+
+enum Finish {
+    Done(u64),
+    Exited(i32),
+    Stopped(String),
+    Limit,
+    Timeout,
 }
 
 static STATUS: LazyLock<Mutex<Status>> = LazyLock::new(Default::default);
 
 impl Command {
     pub fn execute(self, mut debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
-        let mut status = STATUS.lock().unwrap();
-        let status = &mut *status;
+        let mut guard = STATUS.lock().unwrap();
+        let status = &mut *guard;
 
         moveit!{
             let mut target = debugger.as_mut().GetSelectedTarget();
             let mut process = target.as_mut().GetProcess();
         }
 
-        if let Some(addr) = self.record.as_ref() {
-            let addr = u64ptr(&addr)?;
-            status.records.push(addr);
+        if let Some(spec) = self.record.as_ref() {
+            let (location, regs) = parse_record(spec);
+            let point = new_point(target.as_mut(), status, spec, location)?;
+            status.records.push(Record { point, regs, hits: 0 });
         }
-        
-        if let Some(addr) = self.done.as_ref() {
-            let addr = u64ptr(&addr)?;
-            status.done.push(addr);
+
+        if let Some(spec) = self.done.as_ref() {
+            let point = new_point(target.as_mut(), status, spec, spec)?;
+            status.done.push(point);
+        }
+
+        if let Some(sub) = self.sub {
+            return sub.execute(target.as_mut(), status);
+        }
+
+        if self.dump {
+            dump(status, self.out.as_ref())?;
         }
 
         if !self.start {
@@ -64,26 +197,669 @@ impl Command {
             anyhow::bail!("need exit point");
         }
 
+        // modules may be loaded at another address since the points were added
+        for point in status.records.iter_mut().map(|record| &mut record.point).chain(status.done.iter_mut()) {
+            point.load = relocate(target.as_mut(), point.module.as_deref(), point.addr)
+                .with_context(|| format!("relocate #{} {:?} failed", point.id, point.spec))?;
+        }
+
         moveit!{
-            let mut broadcast = process.as_ref().GetBroadcaster();
-            let mut listener = debugger.as_mut().GetListener();
+            let mut thread = process.as_mut().GetSelectedThread();
+            let broadcast = process.as_ref().GetBroadcaster();
+            let mut listener = unsafe { lldb::SBListener::new1(c"mydbg-trace".as_ptr()) };
             let mut event = lldb::SBEvent::new();
         }
 
+        anyhow::ensure!(thread.as_mut().IsValid(), "no selected thread");
+
+        // eBroadcastBitStateChanged
         listener.as_mut().StartListeningForEvents(&broadcast, 1 << 0);
 
+        let is_async = debugger.as_mut().GetAsync();
+        debugger.as_mut().SetAsync(true);
+
+        status.logs.clear();
+        for record in status.records.iter_mut() {
+            record.hits = 0;
+        }
+
+        let ret = if self.fast {
+            run(
+                target.as_mut(),
+                process.as_mut(),
+                listener.as_mut(),
+                event.as_mut(),
+                guard,
+                self.timeout.0
+            )
+        } else {
+            step(
+                process.as_mut(),
+                thread.as_mut(),
+                listener.as_mut(),
+                event.as_mut(),
+                status,
+                self.max_steps,
+                self.timeout.0
+            )
+        };
+
+        debugger.as_mut().SetAsync(is_async);
+        listener.as_mut().StopListeningForEvents(&broadcast, 1 << 0);
+
+        let (finish, steps) = ret?;
+        let unit = if self.fast { "record hits" } else { "steps" };
+
+        match finish {
+            Finish::Done(pc) => println!("reach exit point {:018p} after {} {}", pc as *const u8, steps, unit),
+            Finish::Exited(code) => println!("process exited with status {} after {} {}", code, steps, unit),
+            Finish::Stopped(reason) => println!("stopped by {} after {} {}", reason, steps, unit),
+            Finish::Limit => anyhow::bail!("step limit reached: {}", steps),
+            Finish::Timeout => anyhow::bail!("timeout after {} {}", steps, unit)
+        }
+
+        Ok(())
+    }
+}
+
+/// Single-step `thread` until an exit point is reached, logging every executed pc.
+fn step(
+    mut process: Pin<&mut lldb::SBProcess>,
+    mut thread: Pin<&mut lldb::SBThread>,
+    mut listener: Pin<&mut lldb::SBListener>,
+    mut event: Pin<&mut lldb::SBEvent>,
+    status: &mut Status,
+    max_steps: u64,
+    timeout: Duration,
+) -> anyhow::Result<(Finish, u64)> {
+    let deadline = Instant::now() + timeout;
+    let index = thread.GetIndexID();
+
+    moveit!(let mut error = lldb::SBError::new());
+
+    let mut steps = 0;
+
+    loop {
+        // the pc is read before the step, so it is the instruction about to be executed
+        moveit!(let mut frame = thread.as_mut().GetFrameAtIndex(0));
+        let pc = frame.GetPC();
+
+        if status.done.iter().any(|point| point.load == pc) {
+            return Ok((Finish::Done(pc), steps));
+        }
+
+        if steps == max_steps {
+            return Ok((Finish::Limit, steps));
+        }
+
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok((Finish::Timeout, steps));
+        }
+
+        let mut recorded = false;
+        for record in status.records.iter_mut() {
+            if record.point.load != pc {
+                continue
+            }
+
+            recorded = true;
+            let log = log_hit(frame.as_mut(), record, index, pc);
+            status.logs.push(log);
+        }
+
+        if !recorded {
+            status.logs.push(Log { thread: index, pc, hit: None });
+        }
+
+        error.as_mut().Clear();
+        thread.as_mut().StepInstruction1(false, error.as_mut());
+        if !error.Success() {
+            let err_msg = cstr!(unsafe error.GetCString());
+            anyhow::bail!("step failed: {:?}", err_msg);
+        }
+
+        steps += 1;
+
+        let state = match wait_for_stop(listener.as_mut(), event.as_mut(), left) {
+            Ok(state) => state,
+            Err(_) if Instant::now() >= deadline => {
+                // leave the process stopped for the user
+                moveit!(let _err = process.as_mut().Stop());
+                let _ = wait_for_stop(listener.as_mut(), event.as_mut(), Duration::from_secs(10));
+                return Ok((Finish::Timeout, steps));
+            },
+            Err(err) => return Err(err)
+        };
+
+        match state {
+            lldb::StateType::eStateStopped | lldb::StateType::eStateSuspended => (),
+            lldb::StateType::eStateExited => return Ok((Finish::Exited(process.as_mut().GetExitStatus()), steps)),
+            state => return Ok((Finish::Stopped(format!("state {:?}", state as u32)), steps))
+        }
+
+        moveit!(let frame = thread.as_mut().GetFrameAtIndex(0));
+        let pc = frame.GetPC();
+
+        match thread.as_mut().GetStopReason() {
+            lldb::StopReason::eStopReasonTrace
+                | lldb::StopReason::eStopReasonPlanComplete
+                | lldb::StopReason::eStopReasonNone => (),
+            lldb::StopReason::eStopReasonBreakpoint => {
+                let id = thread.as_mut().GetStopReasonDataAtIndex(0);
+                return Ok((Finish::Stopped(format!("breakpoint {} at {:018p}", id, pc as *const u8)), steps));
+            },
+            lldb::StopReason::eStopReasonSignal => {
+                let signo = thread.as_mut().GetStopReasonDataAtIndex(0);
+                return Ok((Finish::Stopped(format!("signal {} at {:018p}", signo, pc as *const u8)), steps));
+            },
+            reason => return Ok((Finish::Stopped(format!("stop reason {:?} at {:018p}", reason as u32, pc as *const u8)), steps))
+        }
+    }
+}
+
+impl Sub {
+    fn execute(self, mut target: Pin<&mut lldb::SBTarget>, status: &mut Status) -> anyhow::Result<()> {
+        match self {
+            Sub::List(_) => {
+                let mut stdout = io::stdout().lock();
+
+                let points = status.records.iter()
+                    .map(|record| ("record", &record.point, record.regs.join(",")))
+                    .chain(status.done.iter().map(|point| ("done", point, String::new())));
+                for (kind, point, regs) in points {
+                    write!(stdout, "#{} {} {:?} {:018p}", point.id, kind, point.spec, point.load as *const u8)?;
+                    if let Some(module) = point.module.as_ref() {
+                        write!(stdout, " ({}+{:#x})", module, point.addr)?;
+                    }
+                    if !regs.is_empty() {
+                        write!(stdout, " regs= {}", regs)?;
+                    }
+                    writeln!(stdout)?;
+                }
+
+                stdout.flush()?;
+            },
+            Sub::Remove(Remove { id }) => {
+                let count = status.records.len() + status.done.len();
+                status.records.retain(|record| record.point.id != id);
+                status.done.retain(|point| point.id != id);
+                anyhow::ensure!(count != status.records.len() + status.done.len(), "trace point not found: #{}", id);
+                status.logs.retain(|log| log.hit.as_ref().is_none_or(|hit| hit.record != id));
+            },
+            Sub::Clear(_) => *status = Status::default(),
+            Sub::Save(Save { file }) => {
+                let mut output = String::from("# mydbg trace\n");
+
+                let points = status.records.iter()
+                    .map(|record| (true, &record.point, record.regs.clone()))
+                    .chain(status.done.iter().map(|point| (false, point, Vec::new())));
+                for (record, point, regs) in points {
+                    let saved = Saved {
+                        record,
+                        module: point.module.clone(),
+                        addr: point.addr,
+                        regs,
+                        spec: point.spec.clone()
+                    };
+                    output.push_str(&saved.to_line());
+                    output.push('\n');
+                }
+
+                std::fs::write(&file, output)
+                    .with_context(|| format!("write file failed: {:?}", file))?;
+                println!("save {} points to {:?}", status.records.len() + status.done.len(), file);
+            },
+            Sub::Load(Load { file }) => {
+                let input = std::fs::read_to_string(&file)
+                    .with_context(|| format!("read file failed: {:?}", file))?;
+                let mut count = 0;
+
+                for (n, line) in input.lines().enumerate() {
+                    if line.is_empty() || line.starts_with('#') {
+                        continue
+                    }
+
+                    let saved = Saved::from_line(line)
+                        .with_context(|| format!("bad line {}: {:?}", n + 1, line))?;
+
+                    // the module may not be loaded yet, it is relocated again at start
+                    let load = relocate(target.as_mut(), saved.module.as_deref(), saved.addr)
+                        .unwrap_or(u64::MAX);
+
+                    let point = Point {
+                        id: status.next_id,
+                        spec: saved.spec,
+                        module: saved.module,
+                        addr: saved.addr,
+                        load
+                    };
+                    status.next_id += 1;
+
+                    if saved.record {
+                        status.records.push(Record { point, regs: saved.regs, hits: 0 });
+                    } else {
+                        status.done.push(point);
+                    }
+                    count += 1;
+                }
+
+                println!("load {} points from {:?}", count, file);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Saved {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{:#x}\t{}\t{}",
+            if self.record { "record" } else { "done" },
+            self.module.as_deref().unwrap_or("-"),
+            self.addr,
+            if self.regs.is_empty() { "-".into() } else { self.regs.join(",") },
+            self.spec
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Saved> {
+        let mut fields = line.splitn(5, '\t');
+
+        let record = match fields.next()? {
+            "record" => true,
+            "done" => false,
+            _ => return None
+        };
+        let module = Some(fields.next()?).filter(|module| *module != "-").map(String::from);
+        // `u64ptr` only decodes whole bytes, `{:#x}` may have an odd number of digits
+        let addr = u64::from_str_radix(fields.next()?.strip_prefix("0x")?, 16).ok()?;
+        let regs = match fields.next()? {
+            "-" => Vec::new(),
+            regs => regs.split(',').map(String::from).collect()
+        };
+        let spec = fields.next()?.to_owned();
+
+        Some(Saved { record, module, addr, regs, spec })
+    }
+}
+
+fn new_point(
+    mut target: Pin<&mut lldb::SBTarget>,
+    status: &mut Status,
+    spec: &str,
+    location: &str,
+) -> anyhow::Result<Point> {
+    let load = resolve_location(target.as_mut(), location)?;
+    let (module, addr) = locate(target.as_mut(), load);
+
+    let id = status.next_id;
+    status.next_id += 1;
+
+    Ok(Point { id, spec: spec.to_owned(), module, addr, load })
+}
+
+/// Module file name and file address of a load address.
+fn locate(mut target: Pin<&mut lldb::SBTarget>, load: u64) -> (Option<String>, u64) {
+    moveit!{
+        let mut address = target.as_mut().ResolveLoadAddress(load);
+        let mut module = address.as_mut().GetModule();
+        let filespec = module.as_ref().GetFileSpec();
+    }
+
+    match cstr!(unsafe filespec.GetFilename()) {
+        Some(name) if module.as_mut().IsValid() =>
+            (Some(name.to_string_lossy().into_owned()), address.as_mut().GetFileAddress()),
+        _ => (None, load)
+    }
+}
+
+/// Load address of a file address in `module`.
+fn relocate(mut target: Pin<&mut lldb::SBTarget>, module: Option<&str>, addr: u64) -> anyhow::Result<u64> {
+    let name = match module {
+        Some(name) => name,
+        None => return Ok(addr)
+    };
+
+    let modules = target.as_mut().GetNumModules();
+    for module_idx in 0..modules {
         moveit!{
-            let mut err = process.as_mut().Continue();
+            let mut module = target.as_mut().GetModuleAtIndex(module_idx);
+            let filespec = module.as_ref().GetFileSpec();
+        }
+
+        if cstr!(unsafe filespec.GetFilename()).is_none_or(|filename| filename.to_bytes() != name.as_bytes()) {
+            continue
         }
 
-        let _err = err;
+        moveit!(let mut address = module.as_mut().ResolveFileAddress(addr));
+        let load = address.as_mut().GetLoadAddress(&*target);
+        anyhow::ensure!(load != u64::MAX, "module not loaded: {}", name);
+
+        return Ok(load);
+    }
+
+    anyhow::bail!("module not found: {}", name)
+}
+
+/// Continue with breakpoints at record points, whose callback logs the hit
+/// and keeps running, until a breakpoint at an exit point is hit.
+fn run(
+    mut target: Pin<&mut lldb::SBTarget>,
+    mut process: Pin<&mut lldb::SBProcess>,
+    mut listener: Pin<&mut lldb::SBListener>,
+    mut event: Pin<&mut lldb::SBEvent>,
+    guard: MutexGuard<'_, Status>,
+    timeout: Duration,
+) -> anyhow::Result<(Finish, u64)> {
+    let mut breakpoints = Vec::new();
+    let mut done = Vec::new();
+
+    for record in guard.records.iter() {
+        moveit!(let mut bp = target.as_mut().BreakpointCreateByAddress(record.point.load));
 
-        if !listener.as_mut().WaitForEvent(10, event.as_mut()) {
-            println!("false false");
+        // # Safety
+        //
+        // lldb keeps the callback in the breakpoint, not in this SBBreakpoint
+        unsafe {
+            let bp: *mut lldb::SBBreakpoint = bp.as_mut().get_unchecked_mut();
+            mydbg_trace_set_callback(bp.cast(), record.point.id);
         }
 
-        println!("wait wait");
-      
-        Ok(())        
+        breakpoints.push(bp.as_mut().GetID());
     }
+
+    for point in guard.done.iter() {
+        moveit!(let mut bp = target.as_mut().BreakpointCreateByAddress(point.load));
+
+        let id = bp.as_mut().GetID();
+        breakpoints.push(id);
+        done.push(id);
+    }
+
+    // breakpoint callbacks lock the status again
+    drop(guard);
+
+    let ret = run_until_done(process.as_mut(), listener.as_mut(), event.as_mut(), &done, timeout);
+
+    for id in breakpoints {
+        target.as_mut().BreakpointDelete(id);
+    }
+
+    let hits = STATUS.lock().unwrap().records.iter()
+        .map(|record| record.hits)
+        .sum();
+
+    Ok((ret?, hits))
+}
+
+fn run_until_done(
+    mut process: Pin<&mut lldb::SBProcess>,
+    mut listener: Pin<&mut lldb::SBListener>,
+    mut event: Pin<&mut lldb::SBEvent>,
+    done: &[i32],
+    timeout: Duration,
+) -> anyhow::Result<Finish> {
+    moveit!(let err = process.as_mut().Continue());
+    if !err.Success() {
+        let err_msg = cstr!(unsafe err.GetCString());
+        anyhow::bail!("continue failed: {:?}", err_msg);
+    }
+
+    // auto-continue stops are restarted events and skipped
+    let state = match wait_for_stop(listener.as_mut(), event.as_mut(), timeout) {
+        Ok(state) => state,
+        Err(_) => {
+            moveit!(let _err = process.as_mut().Stop());
+            let _ = wait_for_stop(listener.as_mut(), event.as_mut(), Duration::from_secs(10));
+            return Ok(Finish::Timeout);
+        }
+    };
+
+    match state {
+        lldb::StateType::eStateStopped | lldb::StateType::eStateSuspended => (),
+        lldb::StateType::eStateExited => return Ok(Finish::Exited(process.as_mut().GetExitStatus())),
+        state => return Ok(Finish::Stopped(format!("state {:?}", state as u32)))
+    }
+
+    moveit!{
+        let mut thread = process.as_mut().GetSelectedThread();
+        let mut frame = thread.as_mut().GetFrameAtIndex(0);
+    }
+    let pc = frame.GetPC();
+
+    match thread.as_mut().GetStopReason() {
+        lldb::StopReason::eStopReasonBreakpoint => {
+            // (breakpoint id, location id) pairs, a site may be shared by record and done points
+            let count = thread.as_mut().GetStopReasonDataCount();
+            let ids = (0..count).step_by(2)
+                .map(|i| thread.as_mut().GetStopReasonDataAtIndex(i as u32))
+                .collect::<Vec<_>>();
+            let id = ids.first().copied().unwrap_or_default();
+
+            if ids.iter().any(|&id| done.iter().any(|&done| done as u64 == id)) {
+                Ok(Finish::Done(pc))
+            } else {
+                Ok(Finish::Stopped(format!("breakpoint {} at {:018p}", id, pc as *const u8)))
+            }
+        },
+        lldb::StopReason::eStopReasonSignal => {
+            let signo = thread.as_mut().GetStopReasonDataAtIndex(0);
+            Ok(Finish::Stopped(format!("signal {} at {:018p}", signo, pc as *const u8)))
+        },
+        reason => Ok(Finish::Stopped(format!("stop reason {:?} at {:018p}", reason as u32, pc as *const u8)))
+    }
+}
+
+extern "C" {
+    /// `SBBreakpoint::SetCallback` shim in plugin.cpp, autocxx can not pass function pointers.
+    fn mydbg_trace_set_callback(bp: *mut libc::c_void, id: usize);
+}
+
+/// Breakpoint callback of record point `id`, `thread` is the thread that hit it.
+///
+/// Returns false so the process keeps running.
+#[no_mangle]
+pub unsafe extern "C" fn mydbg_trace_hit(thread: *mut libc::c_void, id: usize) -> bool {
+    let mut thread = Pin::new_unchecked(&mut *(thread as *mut lldb::SBThread));
+
+    let mut status = match STATUS.lock() {
+        Ok(status) => status,
+        Err(_) => return false
+    };
+    let status = &mut *status;
+
+    moveit!(let mut frame = thread.as_mut().GetFrameAtIndex(0));
+    let pc = frame.GetPC();
+
+    if let Some(record) = status.records.iter_mut().find(|record| record.point.id == id) {
+        let log = log_hit(frame.as_mut(), record, thread.GetIndexID(), pc);
+        status.logs.push(log);
+    }
+
+    false
+}
+
+fn log_hit(mut frame: Pin<&mut lldb::SBFrame>, record: &mut Record, thread: u32, pc: u64) -> Log {
+    record.hits += 1;
+
+    let values = record.regs.iter()
+        .map(|expr| read_value(frame.as_mut(), expr))
+        .collect();
+
+    Log {
+        thread,
+        pc,
+        hit: Some(Hit { record: record.point.id, count: record.hits, values })
+    }
+}
+
+/// Read a register, or evaluate an expression such as `*(long*)$rsi`.
+fn read_value(mut frame: Pin<&mut lldb::SBFrame>, expr: &str) -> Option<u64> {
+    let expr = CString::new(expr).ok()?;
+    moveit!(let mut reg = unsafe { frame.as_mut().FindRegister(expr.as_ptr()) });
+
+    if reg.as_mut().IsValid() {
+        return Some(reg.as_mut().GetValueAsUnsigned1(0));
+    }
+
+    moveit!{
+        let mut value = unsafe { frame.as_mut().EvaluateExpression(expr.as_ptr()) };
+        let error = value.as_mut().GetError();
+    }
+
+    if error.Success() {
+        Some(value.as_mut().GetValueAsUnsigned1(0))
+    } else {
+        None
+    }
+}
+
+/// Resolve an address or symbol name to a load address.
+fn resolve_location(mut target: Pin<&mut lldb::SBTarget>, location: &str) -> anyhow::Result<u64> {
+    if let Ok(addr) = u64ptr(location) {
+        return Ok(addr);
+    }
+
+    let name = CString::new(location)?;
+
+    moveit!{
+        let list = unsafe { target.as_mut().FindSymbols(name.as_ptr(), lldb::SymbolType::eSymbolTypeCode) };
+    }
+
+    for i in 0..list.GetSize() {
+        moveit!{
+            let mut context = list.GetContextAtIndex(i);
+            let mut symbol = context.as_mut().GetSymbol();
+            let mut start = symbol.as_mut().GetStartAddress();
+        }
+
+        let addr = start.as_mut().GetLoadAddress(&*target);
+        if addr != u64::MAX {
+            return Ok(addr);
+        }
+    }
+
+    anyhow::bail!("symbol not found: {:?}", location)
+}
+
+/// Split `<addr|symbol>:<reg|expr,...>` at the first single `:`,
+/// so both symbols and expressions may contain `::`, but expressions can not contain `,`.
+fn parse_record(spec: &str) -> (&str, Vec<String>) {
+    let bytes = spec.as_bytes();
+    let pos = (0..bytes.len()).find(|&i| bytes[i] == b':'
+        && (i == 0 || bytes[i - 1] != b':')
+        && bytes.get(i + 1) != Some(&b':'));
+
+    match pos {
+        Some(pos) if pos + 1 < spec.len() => {
+            let regs = spec[pos + 1..].split(',')
+                .map(str::trim)
+                .filter(|reg| !reg.is_empty())
+                .map(String::from)
+                .collect();
+            (&spec[..pos], regs)
+        },
+        _ => (spec, Vec::new())
+    }
+}
+
+fn dump(status: &Status, out: Option<&PathBuf>) -> anyhow::Result<()> {
+    if let Some(out) = out {
+        let mut output = io::BufWriter::new(
+            std::fs::File::create(out)
+                .with_context(|| format!("create file failed: {:?}", out))?
+        );
+
+        writeln!(output, "record,hit,thread,pc,register,value")?;
+        for (log, hit) in status.logs.iter().filter_map(|log| log.hit.as_ref().map(|hit| (log, hit))) {
+            let record = match status.records.iter().find(|record| record.point.id == hit.record) {
+                Some(record) => record,
+                None => continue
+            };
+            for (reg, value) in record.regs.iter().zip(hit.values.iter()) {
+                let value = value.map(|value| format!("{:#x}", value)).unwrap_or_default();
+                writeln!(output, "{},{},{},{:#x},{},{}", hit.record, hit.count, log.thread, log.pc, reg, value)?;
+            }
+        }
+        output.flush()?;
+
+        println!("write to {:?}", out);
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout().lock();
+
+    for record in status.records.iter() {
+        let point = &record.point;
+        writeln!(stdout, "record #{} {:018p} {:?} hits= {}", point.id, point.load as *const u8, point.spec, record.hits)?;
+
+        for (log, hit) in status.logs.iter().filter_map(|log| log.hit.as_ref().map(|hit| (log, hit))) {
+            if hit.record != point.id {
+                continue
+            }
+
+            write!(stdout, "  #{} thread #{}", hit.count, log.thread)?;
+            for (reg, value) in record.regs.iter().zip(hit.values.iter()) {
+                match value {
+                    Some(value) => write!(stdout, " {}= {:#x}", reg, value)?,
+                    None => write!(stdout, " {}= unknown", reg)?
+                }
+            }
+            writeln!(stdout)?;
+        }
+    }
+
+    writeln!(stdout, "{} pc logged", status.logs.len())?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_record() {
+    assert_eq!(parse_record("0x1000:rax,rdi"), ("0x1000", vec!["rax".into(), "rdi".into()]));
+    assert_eq!(parse_record("app::main:x0"), ("app::main", vec!["x0".into()]));
+    assert_eq!(parse_record("app::main"), ("app::main", vec![]));
+    assert_eq!(parse_record("main"), ("main", vec![]));
+    assert_eq!(
+        parse_record("app::main:rdi,*(std::size_t*)$rsi"),
+        ("app::main", vec!["rdi".into(), "*(std::size_t*)$rsi".into()])
+    );
+}
+
+#[test]
+fn test_saved_line() {
+    let saved = Saved {
+        record: true,
+        module: Some("app".into()),
+        addr: 0x1234,
+        regs: vec!["rax".into(), "rdi".into()],
+        spec: "app::main:rax,rdi".into()
+    };
+    let line = saved.to_line();
+    assert_eq!(line, "record\tapp\t0x1234\trax,rdi\tapp::main:rax,rdi");
+    assert_eq!(Saved::from_line(&line), Some(saved));
+
+    let saved = Saved {
+        record: false,
+        module: None,
+        addr: 0x7fff0000,
+        regs: Vec::new(),
+        spec: "0x7fff0000".into()
+    };
+    assert_eq!(Saved::from_line(&saved.to_line()), Some(saved));
+
+    let saved = Saved {
+        record: false,
+        module: Some("app".into()),
+        addr: 0x11390,
+        regs: Vec::new(),
+        spec: "0x401".into()
+    };
+    assert_eq!(Saved::from_line(&saved.to_line()), Some(saved));
+
+    assert_eq!(Saved::from_line("watch\t-\t0x0\t-\tx"), None);
+    assert_eq!(Saved::from_line("done\t-\t0x0"), None);
 }
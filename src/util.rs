@@ -1,5 +1,7 @@
 use std::io::Write;
 use std::pin::Pin;
+use std::ops::Range;
+use autocxx::moveit::moveit;
 use crate::sys::lldb;
 
 
@@ -22,58 +24,208 @@ pub fn print_pretty_bytes(
     base: u64,
     bytes: &[u8],
 ) -> anyhow::Result<()> {
-    use std::fmt;
+    HexDump::default().print(stdout, base, bytes)
+}
 
-    struct HexPrinter<'a>(&'a [u8]);
-    struct AsciiPrinter<'a>(&'a [u8]);
+/// Hexdump renderer.
+///
+/// Without color, highlighted groups are marked with `*`.
+#[derive(Clone, Copy)]
+pub struct HexDump<'a> {
+    /// bytes per line, 8/16/32
+    pub width: usize,
+    /// bytes per group, 1/2/4/8
+    pub group: usize,
+    pub color: bool,
+    /// mapped regions, used to color pointers
+    pub regions: &'a [Region],
+    pub highlight: &'a dyn Fn(usize) -> bool,
+}
 
-    impl fmt::Display for HexPrinter<'_> {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            for &b in self.0.iter() {
-                write!(f, "{:02x} ", b)?;
-            }
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ByteClass {
+    Zero,
+    Printable,
+    Pointer,
+    Entropy,
+    Other,
+    Highlight,
+}
 
-            for _ in self.0.len()..16 {
-                write!(f, "   ")?;
-            }
+fn no_highlight(_: usize) -> bool {
+    false
+}
 
-            Ok(())
+impl Default for HexDump<'_> {
+    fn default() -> Self {
+        HexDump {
+            width: 16,
+            group: 1,
+            color: false,
+            regions: &[],
+            highlight: &no_highlight,
         }
     }
+}
+
+impl ByteClass {
+    fn color(self) -> &'static str {
+        match self {
+            ByteClass::Zero => "\x1b[90m",
+            ByteClass::Printable => "\x1b[32m",
+            ByteClass::Pointer => "\x1b[36m",
+            ByteClass::Entropy => "\x1b[35m",
+            ByteClass::Other => "",
+            ByteClass::Highlight => "\x1b[1;7;31m",
+        }
+    }
+}
+
+impl HexDump<'_> {
+    pub fn print(&self, stdout: &mut dyn Write, base: u64, bytes: &[u8]) -> anyhow::Result<()> {
+        use std::fmt::Write;
+
+        anyhow::ensure!(matches!(self.width, 8 | 16 | 32), "bad width: {}", self.width);
+        anyhow::ensure!(matches!(self.group, 1 | 2 | 4 | 8), "bad group: {}", self.group);
 
-    impl fmt::Display for AsciiPrinter<'_> {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            use std::fmt::Write;
+        let classes = self.classify(base, bytes);
+        let mut line = String::new();
 
-            for &b in self.0.iter() {
+        for (row, chunk) in bytes.chunks(self.width).enumerate() {
+            let offset = row * self.width;
+
+            line.clear();
+            write!(line, "{:018p}: ", (base + offset as u64) as *const u8)?;
+
+            for pos in 0..self.width {
+                match chunk.get(pos) {
+                    Some(b) => self.paint(&mut line, classes[offset + pos], format_args!("{:02x}", b))?,
+                    None => line.push_str("  ")
+                }
+
+                if (pos + 1) % self.group == 0 {
+                    let mut group = (offset + pos + 1 - self.group)..std::cmp::min(offset + pos + 1, bytes.len());
+                    let marked = !self.color && group.any(|i| (self.highlight)(i));
+                    line.push(if marked { '*' } else { ' ' });
+                }
+            }
+
+            line.push(' ');
+
+            for (pos, &b) in chunk.iter().enumerate() {
                 let c = b as char;
                 let c = if c.is_ascii_graphic() {
                     c
                 } else {
                     '.'
                 };
-                f.write_char(c)?;
+                self.paint(&mut line, classes[offset + pos], format_args!("{}", c))?;
             }
 
-            Ok(())
+            writeln!(stdout, "{}", line)?;
         }
+
+        Ok(())
     }
 
-    let addr = base as *const u8;
+    fn paint(&self, line: &mut String, class: ByteClass, value: std::fmt::Arguments) -> std::fmt::Result {
+        use std::fmt::Write;
 
-    for (offset, chunk) in bytes.chunks(16).enumerate() {
-        let addr = addr.wrapping_add(offset * 16);
+        let color = class.color();
 
-        writeln!(
-            stdout,
-            "{:018p}: {} {}",
-            addr,
-            HexPrinter(chunk),
-            AsciiPrinter(chunk)
-        )?;
+        if self.color && !color.is_empty() {
+            write!(line, "{}{}\x1b[0m", color, value)
+        } else {
+            line.write_fmt(value)
+        }
     }
 
-    Ok(())
+    fn classify(&self, base: u64, bytes: &[u8]) -> Vec<ByteClass> {
+        if !self.color {
+            return vec![ByteClass::Other; bytes.len()];
+        }
+
+        let mut classes = bytes.iter()
+            .map(|&b| match b {
+                0 => ByteClass::Zero,
+                b if b.is_ascii_graphic() || b == b' ' => ByteClass::Printable,
+                _ => ByteClass::Other
+            })
+            .collect::<Vec<_>>();
+
+        if !self.regions.is_empty() {
+            let mut i = ((8 - base % 8) % 8) as usize;
+
+            while i + 8 <= bytes.len() {
+                let word = u64::from_le_bytes(bytes[i..][..8].try_into().unwrap());
+
+                if word != 0 && self.regions.iter().any(|region| region.range.contains(&word)) {
+                    classes[i..][..8].fill(ByteClass::Pointer);
+                }
+
+                i += 8;
+            }
+        }
+
+        for (row, chunk) in bytes.chunks(self.width).enumerate() {
+            if is_high_entropy(chunk) {
+                classes[row * self.width..][..chunk.len()]
+                    .iter_mut()
+                    .filter(|class| **class == ByteClass::Other)
+                    .for_each(|class| *class = ByteClass::Entropy);
+            }
+        }
+
+        for (i, class) in classes.iter_mut().enumerate() {
+            if (self.highlight)(i) {
+                *class = ByteClass::Highlight;
+            }
+        }
+
+        classes
+    }
+}
+
+fn is_high_entropy(bytes: &[u8]) -> bool {
+    if bytes.len() < 8 {
+        return false;
+    }
+
+    let mut counts = [0u32; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+
+    let len = bytes.len() as f64;
+    let entropy: f64 = counts.iter()
+        .filter(|&&n| n != 0)
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    let max = std::cmp::min(bytes.len(), 256) as f64;
+
+    entropy >= max.log2() * 0.9
+}
+
+/// Returns the offset ranges where `old` and `new` differ.
+pub fn diff_ranges(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let len = std::cmp::max(old.len(), new.len());
+
+    for i in 0..len {
+        if old.get(i) == new.get(i) {
+            continue
+        }
+
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end += 1,
+            _ => ranges.push(i..i + 1)
+        }
+    }
+
+    ranges
 }
 
 
@@ -133,17 +285,362 @@ pub fn read_memory<'a>(
     }
 }
 
+pub fn write_memory(
+    process: Pin<&mut lldb::SBProcess>,
+    addr: u64,
+    bytes: &[u8],
+    mut error: Pin<&mut lldb::SBError>
+) -> anyhow::Result<()> {
+    error.as_mut().Clear();
+
+    // # Safety
+    //
+    // write raw data to memory
+    let len = unsafe {
+        process.WriteMemory(
+            addr,
+            bytes.as_ptr().cast(),
+            bytes.len(),
+            error.as_mut()
+        )
+    };
+
+    if error.Success() {
+        anyhow::ensure!(len == bytes.len(), "short write?");
+        Ok(())
+    } else {
+        let err_msg = cstr!(unsafe error.GetCString());
+        anyhow::bail!("write memory failed: {:?}", err_msg)
+    }
+}
+
+pub fn dump_memory(
+    mut process: Pin<&mut lldb::SBProcess>,
+    buf: &mut Vec<u8>,
+    addr: u64,
+    size: usize,
+    mut error: Pin<&mut lldb::SBError>,
+    output: &mut dyn Write,
+) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    const CHUNK_SIZE: usize = 16 * 1024;
+
+    for offset in (0..size).step_by(CHUNK_SIZE) {
+        let addr = addr + offset as u64;
+        let size = std::cmp::min(size - offset, CHUNK_SIZE);
+
+        let buf = read_memory(
+            process.as_mut(),
+            buf,
+            addr,
+            size,
+            error.as_mut()
+        ).with_context(|| format!("addr={:p},size={}", addr as *const u8, size))?;
+
+        output.write_all(buf)?;
+    }
+
+    Ok(())
+}
+
+pub struct Region {
+    pub range: Range<u64>,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+    pub name: Option<Vec<u8>>,
+}
+
+impl Region {
+    pub fn perms(&self) -> String {
+        [
+            if self.readable { 'r' } else { '-' },
+            if self.writable { 'w' } else { '-' },
+            if self.executable { 'x' } else { '-' },
+        ].iter().collect()
+    }
+
+    pub fn size(&self) -> anyhow::Result<usize> {
+        use anyhow::Context;
+
+        self.range.end
+            .checked_sub(self.range.start)
+            .and_then(|size| size.try_into().ok())
+            .with_context(|| format!("invalid region addr: {:?}", self.range))
+    }
+}
+
+pub fn memory_regions(process: Pin<&mut lldb::SBProcess>) -> Vec<Region> {
+    moveit!{
+        let mut mem_list = process.GetMemoryRegions();
+        let mut mem = lldb::SBMemoryRegionInfo::new();
+    }
+
+    let mut regions = Vec::new();
+
+    let mem_len = mem_list.GetSize();
+    for mem_idx in 0..mem_len {
+        if !mem_list.as_mut().GetMemoryRegionAtIndex(mem_idx, mem.as_mut()) {
+            continue
+        }
+
+        regions.push(Region {
+            range: mem.as_mut().GetRegionBase()..mem.as_mut().GetRegionEnd(),
+            readable: mem.as_mut().IsReadable(),
+            writable: mem.as_mut().IsWritable(),
+            executable: mem.as_mut().IsExecutable(),
+            name: cstr!(unsafe mem.as_mut().GetName())
+                .map(|name| Vec::from(name.to_bytes())),
+        });
+    }
+
+    regions
+}
+
+pub fn module_name(target: Pin<&mut lldb::SBTarget>, addr: u64) -> Option<Vec<u8>> {
+    moveit!{
+        let mut address = target.ResolveLoadAddress(addr);
+        let mut module = address.as_mut().GetModule();
+        let filespec = module.as_ref().GetFileSpec();
+    }
+
+    cstr!(unsafe filespec.GetFilename())
+        .map(|name| Vec::from(name.to_bytes()))
+}
+
+/// Source location of a frame as `file:line`, inlined frames report the inlined callee.
+pub fn frame_location(frame: Pin<&mut lldb::SBFrame>) -> Option<Vec<u8>> {
+    moveit!{
+        let entry = frame.GetLineEntry();
+        let filespec = entry.GetFileSpec();
+    }
+
+    if !entry.IsValid() {
+        return None;
+    }
+
+    let mut location = cstr!(unsafe filespec.GetFilename())
+        .map(|name| Vec::from(name.to_bytes()))?;
+    location.extend_from_slice(format!(":{}", entry.GetLine()).as_bytes());
+    Some(location)
+}
+
+/// Disassemble `bytes` as if loaded at `addr`, with symbol-relative labels.
+pub fn print_instructions(
+    stdout: &mut dyn Write,
+    mut target: Pin<&mut lldb::SBTarget>,
+    addr: u64,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    use bstr::ByteSlice;
+
+    // # Safety
+    //
+    // lldb copies the buffer into the instruction list
+    moveit!{
+        let mut list = unsafe {
+            target.as_mut().GetInstructions1(addr, bytes.as_ptr().cast(), bytes.len())
+        };
+    }
+
+    let mut last_symbol: Option<Vec<u8>> = None;
+
+    for i in 0..list.as_mut().GetSize() {
+        moveit!{
+            let mut inst = list.as_mut().GetInstructionAtIndex(i as u32);
+            let mut inst_addr = inst.as_mut().GetAddress();
+        }
+
+        let load_addr = inst_addr.as_mut().GetLoadAddress(&*target);
+        let load_addr = if load_addr == u64::MAX {
+            inst_addr.as_mut().GetOffset()
+        } else {
+            load_addr
+        };
+
+        moveit!{
+            let mut address = target.as_mut().ResolveLoadAddress(load_addr);
+            let mut symbol = address.as_mut().GetSymbol();
+            let mut symbol_start = symbol.as_mut().GetStartAddress();
+        }
+
+        let symbol_name = cstr!(unsafe symbol.as_mut().GetName())
+            .map(|name| Vec::from(name.to_bytes()));
+
+        if symbol_name.is_some() && symbol_name != last_symbol {
+            writeln!(stdout, "{}:", symbol_name.as_deref().unwrap_or_default().as_bstr())?;
+        }
+
+        let label = match symbol_name.as_ref() {
+            Some(_) => {
+                let start = symbol_start.as_mut().GetLoadAddress(&*target);
+                format!("<+{}>", load_addr.wrapping_sub(start))
+            },
+            None => String::new()
+        };
+        last_symbol = symbol_name;
+
+        let size = inst.as_mut().GetByteSize();
+        let offset = load_addr.wrapping_sub(addr) as usize;
+        let inst_bytes = bytes.get(offset..).unwrap_or_default();
+        let inst_bytes = &inst_bytes[..std::cmp::min(size, inst_bytes.len())];
+
+        let mnemonic = cstr!(unsafe inst.as_mut().GetMnemonic(&*target))
+            .map(|s| s.to_bytes())
+            .unwrap_or_default();
+        let operands = cstr!(unsafe inst.as_mut().GetOperands(&*target))
+            .map(|s| s.to_bytes())
+            .unwrap_or_default();
+        let comment = cstr!(unsafe inst.as_mut().GetComment(&*target))
+            .map(|s| s.to_bytes())
+            .unwrap_or_default();
+
+        write!(
+            stdout,
+            "{:018p} {:<8} {:<30} {} {}",
+            load_addr as *const u8,
+            label,
+            data_encoding::HEXLOWER.encode(inst_bytes),
+            mnemonic.as_bstr(),
+            operands.as_bstr()
+        )?;
+
+        if !comment.is_empty() {
+            write!(stdout, " ; {}", comment.as_bstr())?;
+        }
+
+        writeln!(stdout)?;
+    }
+
+    Ok(())
+}
+
+/// Byte size with optional unit, e.g. `512`, `32KiB`, `1 MB`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteSize(pub u64);
+
+impl std::str::FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let pos = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (num, unit) = s.split_at(pos);
+        let num: u64 = num.parse().map_err(|err| format!("bad size {:?}: {}", s, err))?;
+
+        let multiple = match unit.trim() {
+            "" | "B" => 1,
+            "K" | "KiB" => 1 << 10,
+            "M" | "MiB" => 1 << 20,
+            "G" | "GiB" => 1 << 30,
+            "KB" | "kB" => 1000,
+            "MB" => 1000 * 1000,
+            "GB" => 1000 * 1000 * 1000,
+            unit => return Err(format!("bad size unit: {:?}", unit))
+        };
+
+        num.checked_mul(multiple)
+            .map(ByteSize)
+            .ok_or_else(|| format!("size overflow: {:?}", s))
+    }
+}
+
+/// Duration with unit, e.g. `10s`, `500ms`, `2m`, plain number is seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HumanDuration(pub std::time::Duration);
+
+impl std::str::FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use std::time::Duration;
+
+        let s = s.trim();
+        let pos = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+        let (num, unit) = s.split_at(pos);
+        let num: f64 = num.parse().map_err(|err| format!("bad duration {:?}: {}", s, err))?;
+
+        let secs = match unit.trim() {
+            "" | "s" => num,
+            "ms" => num / 1000.0,
+            "m" => num * 60.0,
+            "h" => num * 60.0 * 60.0,
+            unit => return Err(format!("bad duration unit: {:?}", unit))
+        };
+
+        Duration::try_from_secs_f64(secs)
+            .map(HumanDuration)
+            .map_err(|err| format!("bad duration {:?}: {}", s, err))
+    }
+}
+
+/// Wait until the process stops, skipping running and restarted events.
+pub fn wait_for_stop(
+    mut listener: Pin<&mut lldb::SBListener>,
+    mut event: Pin<&mut lldb::SBEvent>,
+    timeout: std::time::Duration,
+) -> anyhow::Result<lldb::StateType> {
+    use std::time::Instant;
+
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        anyhow::ensure!(!left.is_zero(), "wait for stop timeout");
+
+        let secs = std::cmp::max(left.as_secs(), 1) as u32;
+        if !listener.as_mut().WaitForEvent(secs, event.as_mut()) {
+            continue
+        }
+
+        let state = lldb::SBProcess::GetStateFromEvent(&event);
+        match state {
+            lldb::StateType::eStateStopped if lldb::SBProcess::GetRestartedFromEvent(&event) => continue,
+            lldb::StateType::eStateStopped
+                | lldb::StateType::eStateCrashed
+                | lldb::StateType::eStateSuspended
+                | lldb::StateType::eStateExited
+                | lldb::StateType::eStateDetached => return Ok(state),
+            _ => continue
+        }
+    }
+}
+
+/// Shell style glob, supports `*` and `?`.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                },
+                None => return false
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
 pub fn u64ptr(value: &str) -> anyhow::Result<u64> {
     use anyhow::Context;
 
     let value = if let Some(value) = value.strip_prefix("0x") {
-        let mut buf = [0; 8];
-        let n = data_encoding::HEXLOWER_PERMISSIVE.decode_len(value.len())?;
-        let n = buf.len().checked_sub(n).context("hex value is greater than 64bit")?;
-        data_encoding::HEXLOWER_PERMISSIVE
-            .decode_mut(value.as_bytes(), &mut buf[n..])
-            .map_err(|err| anyhow::format_err!("hex decode failed: {:?}", err))?;
-        u64::from_be_bytes(buf)
+        u64::from_str_radix(value, 16).context("hex parse failed")?
     } else {
         value.parse::<u64>().context("number parse failed")?
     };
@@ -154,14 +651,78 @@ pub fn u64ptr(value: &str) -> anyhow::Result<u64> {
 fn test_u64ptr_from_str() {
     assert_eq!(
         0x01,
-        u64ptr_from_str("0x01").unwrap()
+        u64ptr("0x01").unwrap()
     );
     assert_eq!(
         0x000056257f77c380,
-        u64ptr_from_str("0x000056257f77c380").unwrap()
+        u64ptr("0x000056257f77c380").unwrap()
     );
     assert_eq!(
         0x0056257f77c38000,
-        u64ptr_from_str("0x0056257f77c38000").unwrap()
+        u64ptr("0x0056257f77c38000").unwrap()
+    );
+    assert_eq!(
+        0x401139b,
+        u64ptr("0x401139b").unwrap()
     );
+    assert!(u64ptr("0x10000000000000000").is_err());
+}
+
+#[test]
+fn test_diff_ranges() {
+    assert!(diff_ranges(b"abcd", b"abcd").is_empty());
+    assert_eq!(diff_ranges(b"abcdef", b"aXXdeY"), vec![1..3, 5..6]);
+    assert_eq!(diff_ranges(b"ab", b"abcd"), vec![2..4]);
+}
+
+#[test]
+fn test_hexdump_layout() {
+    let mut output = Vec::new();
+    print_pretty_bytes(&mut output, 0x10, b"ABC").unwrap();
+    assert_eq!(
+        output,
+        format!("0x0000000000000010: 41 42 43 {} ABC\n", " ".repeat(13 * 3)).into_bytes()
+    );
+
+    let mut output = Vec::new();
+    let dump = HexDump { width: 8, group: 4, highlight: &|i| i == 5, ..HexDump::default() };
+    dump.print(&mut output, 0x10, b"ABCDEFGH").unwrap();
+    assert_eq!(output, b"0x0000000000000010: 41424344 45464748* ABCDEFGH\n");
+}
+
+#[test]
+fn test_is_high_entropy() {
+    assert!(!is_high_entropy(&[0; 16]));
+    assert!(!is_high_entropy(b"aaaabbbbaaaabbbb"));
+    assert!(is_high_entropy(&(0..16).collect::<Vec<u8>>()));
+}
+
+#[test]
+fn test_human_duration_from_str() {
+    use std::time::Duration;
+
+    assert_eq!("10s".parse(), Ok(HumanDuration(Duration::from_secs(10))));
+    assert_eq!("500ms".parse(), Ok(HumanDuration(Duration::from_millis(500))));
+    assert_eq!("2m".parse(), Ok(HumanDuration(Duration::from_secs(120))));
+    assert_eq!("1.5".parse(), Ok(HumanDuration(Duration::from_millis(1500))));
+    assert!("10d".parse::<HumanDuration>().is_err());
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match(b"tokio-*", b"tokio-runtime-worker"));
+    assert!(glob_match(b"*worker", b"tokio-runtime-worker"));
+    assert!(glob_match(b"w?rker-*-1", b"worker-12-1"));
+    assert!(glob_match(b"*", b""));
+    assert!(!glob_match(b"tokio-*", b"main"));
+    assert!(!glob_match(b"worker", b"worker-1"));
+}
+
+#[test]
+fn test_byte_size_from_str() {
+    assert_eq!("512".parse(), Ok(ByteSize(512)));
+    assert_eq!("32KiB".parse(), Ok(ByteSize(32 * 1024)));
+    assert_eq!("1 MB".parse(), Ok(ByteSize(1000 * 1000)));
+    assert!("1 XB".parse::<ByteSize>().is_err());
+    assert!("KiB".parse::<ByteSize>().is_err());
 }
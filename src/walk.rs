@@ -0,0 +1,239 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::str::FromStr;
+use std::collections::HashSet;
+use argh::FromArgs;
+use anyhow::Context;
+use autocxx::moveit::moveit;
+use crate::sys::lldb;
+use crate::read::{ print_type_overlay, type_size };
+use crate::string::{ rust_vec_layout, words };
+use crate::util::{ HexDump, memory_regions, read_memory, u64ptr };
+
+
+/// MyDbg Walk command
+#[derive(FromArgs)]
+pub struct Command {
+    /// first node, or container address with preset
+    #[argh(positional)]
+    address: String,
+
+    /// offset of next pointer in node, default 0
+    #[argh(option, default = "0")]
+    next_offset: u64,
+
+    /// next pointer points to the next pointer field instead of node start
+    #[argh(switch)]
+    intrusive: bool,
+
+    /// max node count, default 1024
+    #[argh(option, default = "1024")]
+    count: usize,
+
+    /// overlay node as type
+    #[argh(option, short = 't', long = "type")]
+    ty: Option<String>,
+
+    /// node or element size, default is type size or 64
+    #[argh(option, short = 's')]
+    size: Option<usize>,
+
+    /// container preset, vector/list/rust-vec/vecdeque
+    #[argh(option)]
+    preset: Option<Preset>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// libstdc++/libc++ `std::vector`: `{ begin, end, cap_end }`
+    Vector,
+    /// libstdc++ `std::list`: `{ next, prev, size }`, node data after `{ next, prev }`
+    List,
+    /// Rust `Vec<T>`
+    RustVec,
+    /// Rust `VecDeque<T>`
+    VecDeque,
+}
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let addr = u64ptr(self.address.as_str())?;
+        let color = debugger.GetUseColor();
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let mut error = lldb::SBError::new();
+        }
+
+        let size = match (self.size, self.ty.as_ref()) {
+            (Some(size), _) => size,
+            (None, Some(ty)) => type_size(target.as_mut(), ty)?,
+            (None, None) if self.preset.is_none() || self.preset == Some(Preset::List) => 64,
+            (None, None) => anyhow::bail!("need element --size or --type")
+        };
+        anyhow::ensure!(size != 0, "element size must be greater than 0");
+
+        let regions = memory_regions(process.as_mut());
+        let is_mapped = |ptr: u64| regions.iter().any(|region| region.range.contains(&ptr));
+
+        let mut buf: Vec<u8> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut truncated = false;
+        let mut broken = None;
+
+        match self.preset {
+            None | Some(Preset::List) => {
+                let (mut node, next_offset, data_offset) = if self.preset.is_some() {
+                    let header = read_memory(process.as_mut(), &mut buf, addr, 8, error.as_mut())?;
+                    (words::<1>(header)[0], 0, 16)
+                } else {
+                    (addr, self.next_offset, 0)
+                };
+                let mut visited = HashSet::new();
+
+                loop {
+                    if node == 0 {
+                        break
+                    }
+
+                    if self.preset.is_some() && node == addr {
+                        break
+                    }
+
+                    if nodes.len() == self.count {
+                        truncated = true;
+                        break
+                    }
+
+                    if !visited.insert(node) {
+                        writeln!(stdout, "cycle detected at {:018p}", node as *const u8)?;
+                        break
+                    }
+
+                    nodes.push(node + data_offset);
+
+                    // keep the nodes walked so far if the chain is broken
+                    let next = match read_memory(process.as_mut(), &mut buf, node + next_offset, 8, error.as_mut()) {
+                        Ok(next) => words::<1>(next)[0],
+                        Err(err) => {
+                            broken = Some(format!("read next of node #{} {:018p} failed: {:?}", nodes.len() - 1, node as *const u8, err));
+                            break
+                        }
+                    };
+
+                    if next == 0 {
+                        break
+                    }
+
+                    node = if self.intrusive {
+                        next.wrapping_sub(next_offset)
+                    } else {
+                        next
+                    };
+                }
+            },
+            Some(Preset::Vector) => {
+                let header = read_memory(process.as_mut(), &mut buf, addr, 24, error.as_mut())?;
+                let [begin, end, cap_end] = words::<3>(header);
+
+                anyhow::ensure!(begin <= end && end <= cap_end, "does not look like a vector: {:x?}", [begin, end, cap_end]);
+
+                let len = (end - begin) / size as u64;
+                writeln!(stdout, "vector len= {} cap= {}", len, (cap_end - begin) / size as u64)?;
+
+                // len comes from target memory, do not trust it for allocation
+                nodes.extend((0..len).take(self.count.saturating_add(1)).map(|i| begin + i * size as u64));
+            },
+            Some(Preset::RustVec) => {
+                let header = read_memory(process.as_mut(), &mut buf, addr, 24, error.as_mut())?;
+                let layout = rust_vec_layout(words::<3>(header), is_mapped)
+                    .ok_or_else(|| anyhow::format_err!("does not look like a Vec: {:x?}", words::<3>(header)))?;
+
+                writeln!(stdout, "Vec len= {} cap= {}", layout.len, layout.cap.unwrap_or_default())?;
+
+                nodes.extend((0..layout.len).take(self.count.saturating_add(1)).map(|i| layout.ptr + i * size as u64));
+            },
+            Some(Preset::VecDeque) => {
+                // `{ head, len, buf: { ptr, cap } }`, field order is not fixed,
+                // so take the mapped word as ptr, the largest as cap,
+                // and the rest as head and len in memory order.
+                let header = read_memory(process.as_mut(), &mut buf, addr, 32, error.as_mut())?;
+                let words = words::<4>(header);
+
+                // empty `VecDeque::new()` holds a dangling pointer and nothing else
+                if let [ptr] = words.iter().filter(|&&word| word != 0).collect::<Vec<_>>()[..] {
+                    if ptr.is_power_of_two() && *ptr <= 4096 && !is_mapped(*ptr) {
+                        writeln!(stdout, "VecDeque head= 0 len= 0 cap= 0")?;
+                        stdout.flush()?;
+                        return Ok(());
+                    }
+                }
+
+                let ptr_idx = (0..4).find(|&i| words[i] != 0 && is_mapped(words[i]))
+                    .with_context(|| format!("does not look like a VecDeque: {:x?}", words))?;
+                let mut rest = (0..4).filter(|&i| i != ptr_idx).collect::<Vec<_>>();
+                let cap_idx = *rest.iter().max_by_key(|&&i| words[i]).unwrap();
+                rest.retain(|&i| i != cap_idx);
+                let (ptr, cap, head, len) = (words[ptr_idx], words[cap_idx], words[rest[0]], words[rest[1]]);
+
+                anyhow::ensure!(head <= cap && len <= cap, "does not look like a VecDeque: {:x?}", words);
+
+                writeln!(stdout, "VecDeque head= {} len= {} cap= {}", head, len, cap)?;
+
+                nodes.extend((0..len).take(self.count.saturating_add(1)).map(|i| ptr + (head + i) % cap * size as u64));
+            }
+        }
+
+        let dump = HexDump {
+            color,
+            regions: if color { &regions[..] } else { &[] },
+            ..HexDump::default()
+        };
+
+        for (i, &node) in nodes.iter().take(self.count).enumerate() {
+            writeln!(stdout, "#{} {:018p}", i, node as *const u8)?;
+
+            let ret = if let Some(ty) = self.ty.as_ref() {
+                print_type_overlay(&mut stdout, target.as_mut(), node, ty, &dump)
+            } else {
+                read_memory(process.as_mut(), &mut buf, node, size, error.as_mut())
+                    .and_then(|bytes| dump.print(&mut stdout, node, bytes))
+            };
+
+            if let Err(err) = ret {
+                broken = Some(format!("read node #{} {:018p} failed: {:?}", i, node as *const u8, err));
+                break
+            }
+
+            writeln!(stdout)?;
+        }
+
+        if let Some(err) = broken {
+            writeln!(stdout, "error: {}", err)?;
+        }
+
+        if truncated || nodes.len() > self.count {
+            writeln!(stdout, "stop after {} nodes", self.count)?;
+        }
+
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "vector" => Preset::Vector,
+            "list" => Preset::List,
+            "rust-vec" => Preset::RustVec,
+            "vecdeque" => Preset::VecDeque,
+            _ => return Err(format!("unknown preset: {}", s))
+        })
+    }
+}
@@ -0,0 +1,236 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::sync::{ Mutex, LazyLock };
+use argh::FromArgs;
+use anyhow::Context;
+use autocxx::moveit::moveit;
+use crate::sys::lldb;
+use crate::util::{ HexDump, diff_ranges, read_memory, u64ptr };
+
+
+/// MyDbg Watch-read command
+#[derive(FromArgs)]
+pub struct Command {
+    /// watch address
+    #[argh(positional)]
+    address: Option<String>,
+
+    /// watch size, default 64
+    #[argh(positional)]
+    size: Option<usize>,
+
+    /// list watches
+    #[argh(switch)]
+    list: bool,
+
+    /// remove watch by id
+    #[argh(option)]
+    remove: Option<usize>,
+
+    /// remove all watches
+    #[argh(switch)]
+    clear: bool,
+}
+
+struct Watch {
+    id: usize,
+    addr: u64,
+    size: usize,
+    last: Option<Vec<u8>>,
+}
+
+#[derive(Default)]
+struct Status {
+    watches: Vec<Watch>,
+    next_id: usize,
+    listening: bool,
+}
+
+static STATUS: LazyLock<Mutex<Status>> = LazyLock::new(Default::default);
+
+impl Command {
+    pub fn execute(self, mut debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let mut status = STATUS.lock().unwrap();
+        let status = &mut *status;
+        let mut stdout = io::stdout().lock();
+
+        if self.clear {
+            status.watches.clear();
+        }
+
+        if let Some(id) = self.remove {
+            let idx = status.watches.iter()
+                .position(|watch| watch.id == id)
+                .with_context(|| format!("no watch #{}", id))?;
+            status.watches.remove(idx);
+        }
+
+        if self.list {
+            for watch in status.watches.iter() {
+                writeln!(
+                    stdout,
+                    "#{} {:018p} size= {}",
+                    watch.id,
+                    watch.addr as *const u8,
+                    watch.size
+                )?;
+            }
+        }
+
+        if let Some(addr) = self.address.as_ref() {
+            let addr = u64ptr(addr)?;
+            let id = status.next_id;
+            status.next_id += 1;
+            status.watches.push(Watch {
+                id,
+                addr,
+                size: self.size.unwrap_or(64),
+                last: None
+            });
+
+            let last = status.watches.len() - 1;
+            refresh(&mut stdout, debugger.as_mut(), &mut status.watches[last..])?;
+
+            if !status.listening {
+                let debugger_id = debugger.GetID();
+
+                std::thread::Builder::new()
+                    .name("mydbg-watch".into())
+                    .spawn(move || {
+                        if let Err(err) = listen(debugger_id) {
+                            println!("watch-read failed: {:?}", err);
+                            STATUS.lock().unwrap().listening = false;
+                        }
+                    })?;
+
+                status.listening = true;
+            }
+        }
+
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Wait for process stop events and refresh all watches, until no watch left.
+///
+/// A re-run or re-attach creates a new process with another broadcaster,
+/// so listen again when the process changes.
+fn listen(debugger_id: u64) -> anyhow::Result<()> {
+    moveit!{
+        let mut debugger = lldb::SBDebugger::FindDebuggerWithID(autocxx::c_int(debugger_id as i32));
+        let mut listener = unsafe { lldb::SBListener::new1(c"mydbg-watch".as_ptr()) };
+        let mut event = lldb::SBEvent::new();
+    }
+
+    loop {
+        moveit!{
+            let mut target = debugger.as_mut().GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let broadcast = process.as_ref().GetBroadcaster();
+        }
+
+        let unique_id = process.as_mut().GetUniqueID();
+
+        // eBroadcastBitStateChanged
+        listener.as_mut().StartListeningForEvents(&broadcast, 1 << 0);
+        let ret = wait_for_events(debugger.as_mut(), listener.as_mut(), event.as_mut(), unique_id);
+        listener.as_mut().StopListeningForEvents(&broadcast, 1 << 0);
+
+        if !ret? {
+            return Ok(());
+        }
+    }
+}
+
+/// Returns true if the process changed, false if no watch left.
+fn wait_for_events(
+    mut debugger: Pin<&mut lldb::SBDebugger>,
+    mut listener: Pin<&mut lldb::SBListener>,
+    mut event: Pin<&mut lldb::SBEvent>,
+    unique_id: u32,
+) -> anyhow::Result<bool> {
+    loop {
+        {
+            // clear the flag under the same lock, a watch added later spawns a new listener
+            let mut status = STATUS.lock().unwrap();
+            if status.watches.is_empty() {
+                status.listening = false;
+                return Ok(false);
+            }
+        }
+
+        moveit!{
+            let mut target = debugger.as_mut().GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+        }
+
+        if process.as_mut().GetUniqueID() != unique_id {
+            return Ok(true);
+        }
+
+        if !listener.as_mut().WaitForEvent(1, event.as_mut()) {
+            continue
+        }
+
+        // exited process keeps the watches until the next run
+        if lldb::SBProcess::GetStateFromEvent(&event) == lldb::StateType::eStateStopped
+            && !lldb::SBProcess::GetRestartedFromEvent(&event)
+        {
+            let mut status = STATUS.lock().unwrap();
+            let mut stdout = io::stdout().lock();
+            refresh(&mut stdout, debugger.as_mut(), &mut status.watches)?;
+            stdout.flush()?;
+        }
+    }
+}
+
+fn refresh(
+    stdout: &mut dyn Write,
+    debugger: Pin<&mut lldb::SBDebugger>,
+    watches: &mut [Watch],
+) -> anyhow::Result<()> {
+    let color = debugger.GetUseColor();
+
+    moveit!{
+        let mut target = debugger.GetSelectedTarget();
+        let mut process = target.as_mut().GetProcess();
+        let mut error = lldb::SBError::new();
+    }
+
+    let mut buf: Vec<u8> = Vec::new();
+
+    for watch in watches.iter_mut() {
+        writeln!(stdout, "watch #{} {:018p}", watch.id, watch.addr as *const u8)?;
+
+        let bytes = match read_memory(
+            process.as_mut(),
+            &mut buf,
+            watch.addr,
+            watch.size,
+            error.as_mut()
+        ) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                writeln!(stdout, "{:?}", err)?;
+                continue
+            }
+        };
+
+        let ranges = watch.last.as_ref()
+            .map(|last| diff_ranges(last, bytes))
+            .unwrap_or_default();
+
+        HexDump {
+            color,
+            highlight: &|i| ranges.iter().any(|range| range.contains(&i)),
+            ..HexDump::default()
+        }.print(stdout, watch.addr, bytes)?;
+        writeln!(stdout)?;
+
+        watch.last = Some(bytes.to_vec());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::io::{ self, Write };
+use std::pin::Pin;
+use std::sync::{ Mutex, LazyLock };
+use argh::FromArgs;
+use anyhow::Context;
+use autocxx::moveit::moveit;
+use crate::sys::lldb;
+use crate::search::Value;
+use crate::util::{ print_pretty_bytes, read_memory, write_memory, u64ptr };
+
+
+/// MyDbg Write command
+#[derive(FromArgs)]
+pub struct Command {
+    /// write address
+    #[argh(positional)]
+    address: Option<String>,
+
+    /// write value, `@path` to write file content
+    #[argh(positional)]
+    value: Option<String>,
+
+    /// value is hex encoded
+    #[argh(switch, short = 'x')]
+    is_hex: bool,
+
+    /// value is 64bit pointer
+    #[argh(switch, short = 'p')]
+    is_64bit_pointer: bool,
+
+    /// read back and compare after write
+    #[argh(switch)]
+    verify: bool,
+
+    /// restore original bytes of the last write
+    #[argh(switch)]
+    undo: bool,
+}
+
+struct Patch {
+    /// pid and lldb unique id, a re-launched process may reuse the pid
+    process: (u64, u32),
+    addr: u64,
+    original: Vec<u8>,
+}
+
+static JOURNAL: LazyLock<Mutex<Vec<Patch>>> = LazyLock::new(Default::default);
+
+impl Command {
+    pub fn execute(self, debugger: Pin<&mut lldb::SBDebugger>) -> anyhow::Result<()> {
+        let mut journal = JOURNAL.lock().unwrap();
+        let mut stdout = io::stdout().lock();
+
+        moveit!{
+            let mut target = debugger.GetSelectedTarget();
+            let mut process = target.as_mut().GetProcess();
+            let mut error = lldb::SBError::new();
+        }
+
+        let mut buf: Vec<u8> = Vec::new();
+        let current = (process.as_mut().GetProcessID(), process.as_mut().GetUniqueID());
+
+        if self.undo {
+            anyhow::ensure!(
+                self.address.is_none() && self.value.is_none(),
+                "--undo takes no address or value"
+            );
+
+            let patch = journal.last().context("nothing to undo")?;
+
+            anyhow::ensure!(
+                patch.process == current,
+                "last write was made to another process, pid {}",
+                patch.process.0
+            );
+
+            write_memory(process.as_mut(), patch.addr, &patch.original, error.as_mut())?;
+
+            writeln!(
+                stdout,
+                "restore {} bytes at {:018p}",
+                patch.original.len(),
+                patch.addr as *const u8
+            )?;
+            print_pretty_bytes(&mut stdout, patch.addr, &patch.original)?;
+
+            journal.pop();
+            stdout.flush()?;
+
+            return Ok(());
+        }
+
+        let addr = u64ptr(self.address.as_deref().context("need address")?)?;
+        let value = self.value.context("need value")?;
+
+        let bytes = match value.strip_prefix('@') {
+            Some(path) if !self.is_hex && !self.is_64bit_pointer => std::fs::read(path)
+                .with_context(|| format!("read file failed: {:?}", path))?,
+            _ => Value::parse(value, self.is_hex, self.is_64bit_pointer)?.to_bytes()
+        };
+
+        anyhow::ensure!(!bytes.is_empty(), "empty value");
+
+        let original = read_memory(
+            process.as_mut(),
+            &mut buf,
+            addr,
+            bytes.len(),
+            error.as_mut()
+        )?.to_vec();
+
+        write_memory(process.as_mut(), addr, &bytes, error.as_mut())?;
+
+        journal.push(Patch { process: current, addr, original });
+
+        writeln!(stdout, "write {} bytes at {:018p}", bytes.len(), addr as *const u8)?;
+
+        if self.verify {
+            let readback = read_memory(
+                process.as_mut(),
+                &mut buf,
+                addr,
+                bytes.len(),
+                error.as_mut()
+            )?;
+
+            print_pretty_bytes(&mut stdout, addr, readback)?;
+
+            if let Some(offset) = readback.iter().zip(&bytes).position(|(x, y)| x != y) {
+                anyhow::bail!(
+                    "verify failed at {:018p}",
+                    (addr + offset as u64) as *const u8
+                );
+            }
+        }
+
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
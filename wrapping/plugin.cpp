@@ -1,6 +1,11 @@
 #include <lldb/API/SBCommandInterpreter.h>
 #include <lldb/API/SBCommandReturnObject.h>
 #include <lldb/API/SBDebugger.h>
+#include <lldb/API/SBBreakpoint.h>
+#include <lldb/API/SBBreakpointLocation.h>
+#include <lldb/API/SBProcess.h>
+#include <lldb/API/SBThread.h>
+#include <cstddef>
 
 namespace lldb {
 	bool PluginInitialize(lldb::SBDebugger debugger);
@@ -12,13 +17,36 @@ extern "C" {
 	bool mydbg_read_do_execute(void* debugger, char **command, void* result);
 	bool mydbg_thread_do_execute(void* debugger, char **command, void* result);
 	bool mydbg_trace_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_dump_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_write_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_str_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_walk_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_watch_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_stacks_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_locks_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_profile_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_futures_do_execute(void* debugger, char **command, void* result);
+	bool mydbg_trace_hit(void* thread, size_t id);
+}
+
+static bool TraceHitCallback(void *baton, lldb::SBProcess &process,
+                             lldb::SBThread &thread,
+                             lldb::SBBreakpointLocation &location) {
+  return mydbg_trace_hit(&thread, (size_t)baton);
+}
+
+extern "C" void mydbg_trace_set_callback(void* bp, size_t id) {
+  ((lldb::SBBreakpoint*)bp)->SetCallback(TraceHitCallback, (void*)id);
 }
 
 class SearchCommand : public lldb::SBCommandPluginInterface {
 public:
   virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
                          lldb::SBCommandReturnObject &result) {
-	return mydbg_search_do_execute(&debugger, command, &result);
+	bool ok = mydbg_search_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
   }
 };
 
@@ -26,7 +54,10 @@ class ReadCommand : public lldb::SBCommandPluginInterface {
 public:
   virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
                          lldb::SBCommandReturnObject &result) {
-	return mydbg_read_do_execute(&debugger, command, &result);
+	bool ok = mydbg_read_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
   }
 };
 
@@ -34,7 +65,10 @@ class ThreadCommand : public lldb::SBCommandPluginInterface {
 public:
   virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
                          lldb::SBCommandReturnObject &result) {
-	return mydbg_thread_do_execute(&debugger, command, &result);
+	bool ok = mydbg_thread_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
   }
 };
 
@@ -42,7 +76,109 @@ class TraceCommand : public lldb::SBCommandPluginInterface {
 public:
   virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
                          lldb::SBCommandReturnObject &result) {
-	return mydbg_trace_do_execute(&debugger, command, &result);
+	bool ok = mydbg_trace_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class DumpCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_dump_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class WriteCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_write_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class StrCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_str_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class WalkCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_walk_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class WatchCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_watch_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class StacksCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_stacks_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class LocksCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_locks_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class ProfileCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_profile_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
+  }
+};
+
+class FuturesCommand : public lldb::SBCommandPluginInterface {
+public:
+  virtual bool DoExecute(lldb::SBDebugger debugger, char **command,
+                         lldb::SBCommandReturnObject &result) {
+	bool ok = mydbg_futures_do_execute(&debugger, command, &result);
+	if (!ok)
+	  result.SetStatus(lldb::eReturnStatusFailed);
+	return ok;
   }
 };
 
@@ -53,5 +189,14 @@ bool lldb::PluginInitialize(lldb::SBDebugger debugger) {
   foo.AddCommand("read", new ReadCommand(), "read value from memory");
   foo.AddCommand("thread", new ThreadCommand(), "print thread info");
   foo.AddCommand("trace", new TraceCommand(), "print thread info");
+  foo.AddCommand("dump", new DumpCommand(), "dump memory regions to directory");
+  foo.AddCommand("write", new WriteCommand(), "write value to memory");
+  foo.AddCommand("str", new StrCommand(), "read C/C++/Rust string from memory");
+  foo.AddCommand("walk", new WalkCommand(), "walk linked list or container");
+  foo.AddCommand("watch-read", new WatchCommand(), "re-read memory on every stop");
+  foo.AddCommand("stacks", new StacksCommand(), "print unique stacks across threads");
+  foo.AddCommand("locks", new LocksCommand(), "find threads blocked on locks and deadlocks");
+  foo.AddCommand("profile", new ProfileCommand(), "sample all threads into folded stacks");
+  foo.AddCommand("futures", new FuturesCommand(), "rank async state machine types by size");
   return true;
 }